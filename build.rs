@@ -0,0 +1,19 @@
+use std::path::Path;
+
+use wayland_scanner::{generate_code, Side};
+
+/// Protocols `wayland-protocols` 0.29 does not ship, vendored in `protocols/`
+static PROTOCOLS: &[&str] = &["cursor-shape-v1", "pointer-gestures-unstable-v1"];
+
+fn main() {
+    let out_dir = std::env::var("OUT_DIR").unwrap();
+    for name in PROTOCOLS {
+        let xml = format!("protocols/{}.xml", name);
+        println!("cargo:rerun-if-changed={}", xml);
+        generate_code(
+            &xml,
+            Path::new(&out_dir).join(format!("{}_client_api.rs", name)),
+            Side::Client,
+        );
+    }
+}
@@ -0,0 +1,206 @@
+use std::io;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use inotify::{EventMask, Inotify, WatchDescriptor, WatchMask};
+use wayland_client::protocol::wl_surface::WlSurface;
+
+use crate::prelude::*;
+use crate::renderer;
+use crate::widget::animation;
+use crate::widget::{Theme, ThemeConfig};
+
+pub trait ThemeCb = Fn(&Theme);
+
+/// Watches the directory of the config file rather than the file itself, as editors often
+/// save by replacing it
+struct Watcher {
+    inotify: Inotify,
+    path: PathBuf,
+    dir: Option<WatchDescriptor>,
+    /// Watch of the directory above, while the config directory doesn't exist
+    parent: Option<WatchDescriptor>,
+}
+
+impl Watcher {
+    fn new(path: PathBuf) -> io::Result<Self> {
+        let mut this = Self {
+            inotify: Inotify::init()?,
+            path,
+            dir: None,
+            parent: None,
+        };
+        this.watch()?;
+        Ok(this)
+    }
+    fn watch(&mut self) -> io::Result<()> {
+        let dir = self.path.parent().ok_or(io::ErrorKind::NotFound)?;
+        let mut watches = self.inotify.watches();
+        let mask = WatchMask::CLOSE_WRITE
+            | WatchMask::CREATE
+            | WatchMask::DELETE
+            | WatchMask::MOVED_FROM
+            | WatchMask::MOVED_TO
+            | WatchMask::DELETE_SELF
+            | WatchMask::ONLYDIR;
+        match watches.add(dir, mask) {
+            Ok(wd) => {
+                self.dir = Some(wd);
+                if let Some(parent) = self.parent.take() {
+                    // Fails if the kernel already dropped it
+                    let _ = watches.remove(parent);
+                }
+                Ok(())
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound && self.parent.is_none() => {
+                let parent = dir.parent().ok_or(io::ErrorKind::NotFound)?;
+                let mask = WatchMask::CREATE | WatchMask::MOVED_TO | WatchMask::ONLYDIR;
+                self.parent = Some(watches.add(parent, mask)?);
+                Ok(())
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+    /// Reads the pending events, returns whether the config file may have changed
+    fn changed(&mut self) -> bool {
+        let mut buffer = [0; 4096];
+        let (mut changed, mut rewatch) = (false, false);
+        let file_name = self.path.file_name();
+        let dir_name = self.path.parent().and_then(|dir| dir.file_name());
+        loop {
+            let events = match self.inotify.read_events(&mut buffer) {
+                Ok(events) => events,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => {
+                    eprintln!("Failed to read config changes: {e}");
+                    break;
+                }
+            };
+            for event in events {
+                if self.dir.as_ref() == Some(&event.wd) {
+                    if event.mask.intersects(EventMask::DELETE_SELF | EventMask::IGNORED) {
+                        self.dir = None;
+                        changed = true;
+                        rewatch = true;
+                    } else {
+                        changed |= event.name == file_name;
+                    }
+                } else if self.parent.as_ref() == Some(&event.wd) {
+                    rewatch |= event.name == dir_name;
+                }
+            }
+        }
+        if rewatch {
+            if let Err(e) = self.watch() {
+                eprintln!("Failed to watch {}: {e}", self.path.display());
+            }
+            // The file may have been moved in along with its directory
+            changed |= self.dir.is_some();
+        }
+        changed
+    }
+}
+
+struct ConfigInner {
+    theme: ThemeConfig,
+    watcher: Option<Watcher>,
+    targets: Vec<(WlSurface, Rc<dyn ThemeCb>)>,
+}
+
+/// The user's settings, reloaded whenever their files change
+#[derive(Clone)]
+pub struct Config {
+    inner: RcCell<ConfigInner>,
+}
+
+impl std::fmt::Debug for Config {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let inner = self.inner.borrow();
+        f.debug_struct(std::any::type_name::<Self>())
+            .field("theme", &inner.theme)
+            .field("watching", &inner.watcher.as_ref().map(|watcher| &watcher.path))
+            .finish()
+    }
+}
+
+impl Config {
+    /// Loads the config files and starts watching them
+    pub fn load() -> Self {
+        let theme = ThemeConfig::load();
+        renderer::set_font_family(theme.current().font_family.as_deref());
+        animation::set_reduce_motion(theme.reduce_motion);
+        let watcher = ThemeConfig::path().and_then(|path| {
+            Watcher::new(path)
+                .map_err(|e| eprintln!("Failed to watch the config, changes need a restart: {e}"))
+                .ok()
+        });
+        Self {
+            inner: ConfigInner {
+                theme,
+                watcher,
+                targets: Vec::new(),
+            }
+            .into(),
+        }
+    }
+    /// Variant of the theme in use
+    pub fn theme(&self) -> Theme {
+        self.inner.borrow().theme.current().clone()
+    }
+    /// Name of the icon theme in use
+    pub fn icon_theme(&self) -> String {
+        self.inner.borrow().theme.icon_theme.clone()
+    }
+    /// Hands the theme to `on_theme` whenever it changes, replacing the previous handler of
+    /// `surface`
+    pub fn set_target(&self, surface: &WlSurface, on_theme: impl ThemeCb + 'static) {
+        let mut inner = self.inner.borrow_mut();
+        inner.targets.retain(|(target, _)| target != surface);
+        inner.targets.push((surface.clone(), Rc::new(on_theme)));
+    }
+    pub fn remove_target(&self, surface: &WlSurface) {
+        self.inner.borrow_mut().targets.retain(|(target, _)| target != surface);
+    }
+    /// Becomes readable when config files change, then [`Config::dispatch`] should be called
+    pub fn fd(&self) -> Option<RawFd> {
+        self.inner.borrow().watcher.as_ref().map(|watcher| watcher.inotify.as_raw_fd())
+    }
+    /// Reloads the files that changed and applies them to every window. A broken file is
+    /// reported and the current settings kept, as it is likely still being edited.
+    pub fn dispatch(&self) {
+        let (targets, theme) = {
+            let mut inner = self.inner.borrow_mut();
+            let Some(watcher) = &mut inner.watcher else {
+                return;
+            };
+            if !watcher.changed() {
+                return;
+            }
+            let path = &watcher.path;
+            let theme = match path.exists() {
+                true => ThemeConfig::load_from(path),
+                false => Ok(ThemeConfig::default()),
+            };
+            let theme = match theme {
+                Ok(theme) => theme,
+                Err(e) => {
+                    eprintln!("Failed to reload the theme from {}: {e:#}", path.display());
+                    return;
+                }
+            };
+            if theme == inner.theme {
+                return;
+            }
+            animation::set_reduce_motion(theme.reduce_motion);
+            inner.theme = theme;
+            let targets: Vec<_> = inner.targets.iter().map(|(_, cb)| cb.clone()).collect();
+            (targets, inner.theme.current().clone())
+        };
+        renderer::set_font_family(theme.font_family.as_deref());
+        for target in targets {
+            target(&theme);
+        }
+    }
+}
@@ -0,0 +1,353 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+
+use wayland_client::protocol::{
+    wl_data_device::{self, WlDataDevice},
+    wl_data_device_manager::WlDataDeviceManager,
+    wl_data_offer::{self, WlDataOffer},
+    wl_data_source::WlDataSource,
+    wl_seat::WlSeat,
+    wl_surface::WlSurface,
+};
+use wayland_client::{Display, Main};
+
+use crate::prelude::*;
+
+pub mod clipboard;
+pub mod dnd;
+pub mod primary_selection;
+
+pub use clipboard::Clipboard;
+pub use primary_selection::PrimarySelection;
+pub use dnd::{DndAction, DragIcon, DragOffer, DragSource, DragSourceEvent, DropCb, DropEvent};
+
+/// Mime types all meaning utf-8 text. Offering one offers them all.
+pub const TEXT_MIME_TYPES: &[&str] = &["text/plain;charset=utf-8", "UTF8_STRING"];
+
+/// `mime_type` and the other names it goes by
+pub fn mime_aliases(mime_type: &str) -> &[&str] {
+    if TEXT_MIME_TYPES.contains(&mime_type) {
+        TEXT_MIME_TYPES
+    } else {
+        &[]
+    }
+}
+
+/// Picks the type in `offered` to ask for when the application wants `wanted`.
+pub fn pick_mime_type(offered: &[String], wanted: &str) -> Option<String> {
+    if offered.iter().any(|offered| offered == wanted) {
+        return Some(wanted.to_owned());
+    }
+    mime_aliases(wanted)
+        .iter()
+        .find(|alias| offered.iter().any(|offered| offered == *alias))
+        .map(|alias| alias.to_string())
+}
+
+/// The mime type `requested` by a peer, as it was offered by the application.
+pub fn offered_mime_type<'a>(offered: &'a [String], requested: &str) -> Option<&'a str> {
+    offered
+        .iter()
+        .find(|offered| *offered == requested || mime_aliases(offered).contains(&requested))
+        .map(String::as_str)
+}
+
+/// All the types to announce for `mime_types`, aliases included
+pub fn with_aliases(mime_types: &[&str]) -> Vec<String> {
+    let mut all: Vec<String> = Vec::new();
+    for mime_type in mime_types {
+        for name in std::iter::once(mime_type).chain(mime_aliases(mime_type)) {
+            if !all.iter().any(|known| known == name) {
+                all.push(name.to_string());
+            }
+        }
+    }
+    all
+}
+
+/// Writes the data for a mime type the application offered, to the peer reading from the file.
+pub trait SendCb = Fn(&str, File);
+
+/// The clipboard and the primary selection, used the same way
+pub trait Selection {
+    /// Offers `mime_types` to other clients. `on_send` writes the data once one of them
+    /// pastes, so it is only produced when needed. `serial` is the one of the input event
+    /// that triggered the copy.
+    fn set_selection(&self, serial: u32, mime_types: &[&str], on_send: impl SendCb + 'static);
+    fn clear_selection(&self, serial: u32);
+    /// What the current selection can be read as, empty if there is none
+    fn mime_types(&self) -> Vec<String>;
+    /// Reads the selection as `mime_type`, or one of its aliases. `None` if the selection is
+    /// empty or not available as that type.
+    fn read(&self, mime_type: &str) -> Option<io::Result<DataRead>>;
+
+    fn set_text(&self, serial: u32, text: String) {
+        self.set_selection(serial, &TEXT_MIME_TYPES[..1], move |_, mut file| {
+            let _ = file.write_all(text.as_bytes());
+        })
+    }
+    fn read_text(&self) -> Option<io::Result<DataRead>> {
+        self.read(TEXT_MIME_TYPES[0])
+    }
+}
+
+/// Data being read from another client in a background thread
+#[derive(Debug)]
+pub struct DataRead {
+    rx: Receiver<io::Result<Vec<u8>>>,
+}
+
+impl DataRead {
+    /// Asks the peer for the data through `receive`, which gets the fd to write to.
+    pub fn start(display: &Display, receive: impl FnOnce(RawFd)) -> io::Result<Self> {
+        let (reader, writer) = io::pipe()?;
+        receive(writer.as_raw_fd());
+        // Our end must stay open until the request actually went out
+        display.flush()?;
+        drop(writer);
+        Ok(Self::spawn_reader(reader))
+    }
+    /// Reads data we offered ourselves. Going through the compositor would wait on our own
+    /// event loop, which is busy waiting for the data.
+    fn local(mime_type: &str, on_send: &dyn SendCb) -> io::Result<Self> {
+        let (reader, writer) = io::pipe()?;
+        let this = Self::spawn_reader(reader);
+        on_send(mime_type, File::from(OwnedFd::from(writer)));
+        Ok(this)
+    }
+    fn spawn_reader(mut reader: io::PipeReader) -> Self {
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let mut data = Vec::new();
+            let _ = tx.send(reader.read_to_end(&mut data).map(|_| data));
+        });
+        Self { rx }
+    }
+    /// `None` while the peer is still writing
+    pub fn try_recv(&self) -> Option<io::Result<Vec<u8>>> {
+        match self.rx.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(io::ErrorKind::BrokenPipe.into())),
+        }
+    }
+}
+
+/// Selection set by the application, as long as no other client replaced it
+struct LocalSource<S> {
+    source: S,
+    mime_types: Vec<String>,
+    on_send: Rc<dyn SendCb>,
+}
+
+impl<S> LocalSource<S> {
+    fn read(&self, mime_type: &str) -> Option<io::Result<DataRead>> {
+        let mime_type = offered_mime_type(&self.mime_types, mime_type)?;
+        Some(DataRead::local(mime_type, &*self.on_send))
+    }
+}
+
+pub(crate) fn file_from_fd(fd: RawFd) -> File {
+    // The fd was just received from the compositor, so we own it
+    unsafe { File::from_raw_fd(fd) }
+}
+
+/// Data another client offered, through the clipboard or drag and drop
+#[derive(Debug, Clone)]
+pub struct DataOffer {
+    offer: WlDataOffer,
+    display: Display,
+}
+
+/// What the offer announced so far, kept in its user data
+struct OfferState {
+    mime_types: Vec<String>,
+    source_actions: DndAction,
+    action: DndAction,
+}
+
+impl Default for OfferState {
+    fn default() -> Self {
+        Self {
+            mime_types: Vec::new(),
+            source_actions: DndAction::None,
+            action: DndAction::None,
+        }
+    }
+}
+
+type OfferStateCell = RefCell<OfferState>;
+
+impl DataOffer {
+    fn init(offer: &Main<WlDataOffer>) {
+        offer.as_ref().user_data().set(OfferStateCell::default);
+        offer.quick_assign(|offer, evt, _| {
+            let Some(state) = offer.as_ref().user_data().get::<OfferStateCell>() else {
+                return;
+            };
+            let mut state = state.borrow_mut();
+            match evt {
+                wl_data_offer::Event::Offer { mime_type } => state.mime_types.push(mime_type),
+                wl_data_offer::Event::SourceActions { source_actions } => {
+                    state.source_actions = source_actions
+                }
+                wl_data_offer::Event::Action { dnd_action } => state.action = dnd_action,
+                _ => (),
+            }
+        });
+    }
+    fn with_state<T>(&self, f: impl FnOnce(&OfferState) -> T) -> Option<T> {
+        let state = self.offer.as_ref().user_data().get::<OfferStateCell>()?;
+        Some(f(&state.borrow()))
+    }
+    pub fn mime_types(&self) -> Vec<String> {
+        self.with_state(|state| state.mime_types.clone()).unwrap_or_default()
+    }
+    /// Reads the data as `mime_type`, or one of its aliases. `None` if it isn't offered.
+    pub fn receive(&self, mime_type: &str) -> Option<io::Result<DataRead>> {
+        let mime_type = pick_mime_type(&self.mime_types(), mime_type)?;
+        Some(DataRead::start(&self.display, |fd| self.offer.receive(mime_type, fd)))
+    }
+    fn destroy(&self) {
+        self.offer.destroy();
+    }
+}
+
+struct DataDeviceInner {
+    manager: Main<WlDataDeviceManager>,
+    device: Main<WlDataDevice>,
+    display: Display,
+    selection: Option<DataOffer>,
+    selection_source: Option<LocalSource<WlDataSource>>,
+    drop_targets: Vec<(WlSurface, Rc<dyn DropCb>)>,
+    drag: Option<DragOffer>,
+    drag_target: Option<Rc<dyn DropCb>>,
+}
+
+impl DataDeviceInner {
+    fn handle_event(&mut self, evt: wl_data_device::Event) -> Option<(Rc<dyn DropCb>, DropEvent)> {
+        match evt {
+            wl_data_device::Event::DataOffer { id } => DataOffer::init(&id),
+            wl_data_device::Event::Selection { id } => {
+                if let Some(old) = self.selection.take() {
+                    old.destroy();
+                }
+                self.selection = id.map(|offer| DataOffer {
+                    offer,
+                    display: self.display.clone(),
+                });
+            }
+            wl_data_device::Event::Enter { serial, surface, x, y, id } => {
+                self.end_drag();
+                // Drags without a source only happen within the client that started them,
+                // and ours always have one
+                let offer = DragOffer::new(
+                    DataOffer {
+                        offer: id?,
+                        display: self.display.clone(),
+                    },
+                    serial,
+                );
+                self.drag = Some(offer.clone());
+                self.drag_target = self
+                    .drop_targets
+                    .iter()
+                    .find(|(target, _)| *target == surface)
+                    .map(|(_, cb)| cb.clone());
+                return Some((self.drag_target.clone()?, DropEvent::Enter { offer, x, y }));
+            }
+            wl_data_device::Event::Motion { time, x, y } => {
+                return Some((self.drag_target.clone()?, DropEvent::Motion { time, x, y }));
+            }
+            wl_data_device::Event::Leave => {
+                let target = self.drag_target.clone();
+                self.end_drag();
+                return Some((target?, DropEvent::Leave));
+            }
+            wl_data_device::Event::Drop => {
+                // The target now owns the offer, until it finishes reading
+                let offer = self.drag.take()?;
+                let target = self.drag_target.take();
+                let Some(target) = target else {
+                    offer.cancel();
+                    return None;
+                };
+                return Some((target, DropEvent::Drop { offer }));
+            }
+            _ => (),
+        }
+        None
+    }
+    fn end_drag(&mut self) {
+        if let Some(offer) = self.drag.take() {
+            offer.cancel();
+        }
+        self.drag_target = None;
+    }
+}
+
+/// The seat's `wl_data_device`, shared by the clipboard and drag and drop
+#[derive(Clone)]
+pub struct DataDevice {
+    inner: RcCell<DataDeviceInner>,
+}
+
+impl std::fmt::Debug for DataDevice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let inner = self.inner.borrow();
+        f.debug_struct(std::any::type_name::<Self>())
+            .field("device", &inner.device)
+            .field("selection", &inner.selection)
+            .field("drag", &inner.drag)
+            .finish()
+    }
+}
+
+impl DataDevice {
+    pub fn new(manager: &Main<WlDataDeviceManager>, seat: &WlSeat, display: &Display) -> Self {
+        let device = manager.get_data_device(seat);
+        let this = Self {
+            inner: DataDeviceInner {
+                manager: manager.clone(),
+                device: device.clone(),
+                display: display.clone(),
+                selection: None,
+                selection_source: None,
+                drop_targets: Vec::new(),
+                drag: None,
+                drag_target: None,
+            }
+            .into(),
+        };
+        device.quick_assign({
+            let this = this.clone();
+            move |_, evt, _| {
+                let dropped = this.inner.borrow_mut().handle_event(evt);
+                if let Some((target, evt)) = dropped {
+                    target(evt);
+                }
+            }
+        });
+        this
+    }
+    pub fn clipboard(&self) -> Clipboard {
+        Clipboard::new(self.clone())
+    }
+    /// Makes `surface` accept drops, replacing its previous handler
+    pub fn set_drop_target(&self, surface: &WlSurface, on_drop: impl DropCb + 'static) {
+        let mut inner = self.inner.borrow_mut();
+        inner.drop_targets.retain(|(target, _)| target != surface);
+        inner.drop_targets.push((surface.clone(), Rc::new(on_drop)));
+    }
+    pub fn remove_drop_target(&self, surface: &WlSurface) {
+        self.inner.borrow_mut().drop_targets.retain(|(target, _)| target != surface);
+    }
+    /// Starts building a drag from `origin`, see [`DragSource`]
+    pub fn drag(&self, origin: &WlSurface, mime_types: &[&str], actions: DndAction) -> DragSource {
+        DragSource::new(self.clone(), origin, mime_types, actions)
+    }
+}
@@ -0,0 +1,79 @@
+use std::io;
+use std::rc::Rc;
+
+use wayland_client::protocol::wl_data_source;
+
+use super::{offered_mime_type, with_aliases, DataDevice, DataRead, LocalSource, SendCb, Selection};
+
+/// Copy and paste through the seat's `wl_data_device`
+#[derive(Debug, Clone)]
+pub struct Clipboard {
+    device: DataDevice,
+}
+
+impl Clipboard {
+    pub fn new(device: DataDevice) -> Self {
+        Self { device }
+    }
+}
+
+impl Selection for Clipboard {
+    fn set_selection(&self, serial: u32, mime_types: &[&str], on_send: impl SendCb + 'static) {
+        let mut inner = self.device.inner.borrow_mut();
+        let source = inner.manager.create_data_source();
+        for mime_type in with_aliases(mime_types) {
+            source.offer(mime_type);
+        }
+        let offered: Vec<String> = mime_types.iter().map(|mime_type| mime_type.to_string()).collect();
+        let on_send: Rc<dyn SendCb> = Rc::new(on_send);
+        source.quick_assign({
+            let device = self.device.clone();
+            let offered = offered.clone();
+            let on_send = on_send.clone();
+            move |source, evt, _| match evt {
+                wl_data_source::Event::Send { mime_type, fd } => {
+                    let file = super::file_from_fd(fd);
+                    if let Some(mime_type) = offered_mime_type(&offered, &mime_type) {
+                        on_send(mime_type, file)
+                    }
+                }
+                // Someone else took the selection
+                wl_data_source::Event::Cancelled => {
+                    let mut inner = device.inner.borrow_mut();
+                    if inner.selection_source.as_ref().is_some_and(|local| local.source == **source) {
+                        inner.selection_source = None;
+                    }
+                    source.destroy();
+                }
+                _ => (),
+            }
+        });
+        inner.device.set_selection(Some(&source), serial);
+        inner.selection_source = Some(LocalSource {
+            source: (**source).clone(),
+            mime_types: offered,
+            on_send,
+        });
+    }
+    fn clear_selection(&self, serial: u32) {
+        let mut inner = self.device.inner.borrow_mut();
+        inner.device.set_selection(None, serial);
+        inner.selection_source = None;
+    }
+    fn mime_types(&self) -> Vec<String> {
+        let inner = self.device.inner.borrow();
+        if let Some(local) = &inner.selection_source {
+            return with_aliases(&local.mime_types.iter().map(String::as_str).collect::<Vec<_>>());
+        }
+        inner.selection.as_ref().map(|offer| offer.mime_types()).unwrap_or_default()
+    }
+    fn read(&self, mime_type: &str) -> Option<io::Result<DataRead>> {
+        let inner = self.device.inner.borrow();
+        if let Some(local) = &inner.selection_source {
+            return local.read(mime_type);
+        }
+        let selection = inner.selection.clone()?;
+        drop(inner);
+        selection.receive(mime_type)
+    }
+}
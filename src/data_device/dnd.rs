@@ -0,0 +1,227 @@
+use std::io;
+
+use wayland_client::protocol::{wl_data_source, wl_surface::WlSurface};
+use wayland_client::Main;
+
+pub use wayland_client::protocol::wl_data_device_manager::DndAction;
+
+use crate::get_globals;
+use crate::renderer::{Canvas, FrameBuffer};
+
+use super::{file_from_fd, offered_mime_type, pick_mime_type, with_aliases, DataDevice, DataOffer, DataRead, SendCb};
+
+/// Sent to a drop target while a drag is over it
+#[derive(Debug)]
+pub enum DropEvent {
+    /// Accept a mime type and set the actions on `offer` to allow dropping.
+    Enter { offer: DragOffer, x: f64, y: f64 },
+    Motion { time: u32, x: f64, y: f64 },
+    Leave,
+    /// The data can now be read from `offer`, which must be finished or cancelled afterwards.
+    Drop { offer: DragOffer },
+}
+
+pub trait DropCb = Fn(DropEvent);
+
+/// Data being dragged over one of our surfaces
+#[derive(Debug, Clone)]
+pub struct DragOffer {
+    offer: DataOffer,
+    serial: u32,
+}
+
+impl DragOffer {
+    pub(super) fn new(offer: DataOffer, serial: u32) -> Self {
+        Self { offer, serial }
+    }
+    pub fn mime_types(&self) -> Vec<String> {
+        self.offer.mime_types()
+    }
+    /// Actions the source allows
+    pub fn source_actions(&self) -> DndAction {
+        self.offer.with_state(|state| state.source_actions).unwrap_or(DndAction::None)
+    }
+    /// Action the compositor picked out of the source's and ours
+    pub fn action(&self) -> DndAction {
+        self.offer.with_state(|state| state.action).unwrap_or(DndAction::None)
+    }
+    /// Tells the source which type would be read on drop, `None` refuses the drop.
+    pub fn accept(&self, mime_type: Option<&str>) {
+        let mime_type = mime_type.and_then(|mime_type| pick_mime_type(&self.mime_types(), mime_type));
+        self.offer.offer.accept(self.serial, mime_type);
+    }
+    /// `preferred` must be a single action out of `actions`. Only copy is possible with
+    /// compositors older than version 3.
+    pub fn set_actions(&self, actions: DndAction, preferred: DndAction) {
+        if self.offer.offer.as_ref().version() >= 3 {
+            self.offer.offer.set_actions(actions, preferred);
+        }
+    }
+    /// Reads the data as `mime_type`, or one of its aliases. `None` if it isn't offered.
+    pub fn receive(&self, mime_type: &str) -> Option<io::Result<DataRead>> {
+        self.offer.receive(mime_type)
+    }
+    /// Done reading a dropped offer. After an ask action, the final one must be set first.
+    pub fn finish(self) {
+        if self.offer.offer.as_ref().version() >= 3 {
+            self.offer.offer.finish();
+        }
+        self.offer.destroy();
+    }
+    /// Refuses a dropped offer, or drops one that is no longer needed
+    pub fn cancel(self) {
+        self.offer.destroy();
+    }
+}
+
+/// What happens to a drag we started
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DragSourceEvent {
+    /// The type the target would read, `None` if it wouldn't take the drop
+    Target(Option<String>),
+    Action(DndAction),
+    DropPerformed,
+    /// The target is done reading. With [`DndAction::Move`] the data should be removed.
+    Finished(DndAction),
+    Cancelled,
+}
+
+pub trait DragCb = Fn(DragSourceEvent);
+
+/// Surface shown under the pointer while dragging
+#[derive(Debug)]
+pub struct DragIcon {
+    surface: Main<WlSurface>,
+}
+
+impl DragIcon {
+    /// `width`x`height` icon drawn by `draw`, with `hotspot` under the pointer
+    pub fn new(width: i32, height: i32, hotspot: (i32, i32), draw: impl FnOnce(&mut Canvas)) -> Self {
+        let globals = get_globals();
+        let surface = globals.wl_compositor.create_surface();
+        let mut frame = FrameBuffer::default();
+        frame.resize(width.max(0) as usize, height.max(0) as usize);
+        draw(&mut frame.canvas());
+        let format = globals.pixel_format().expect("No pixel format to draw the icon in");
+        let mut shm_pool = globals.shm_pool.borrow_mut();
+        let (buf, wl_buf) = shm_pool
+            .buffer(width, height, width * format.bytes_per_pixel() as i32, format.wl_format())
+            .unwrap();
+        buf.copy_from_slice(frame.converted(format, &[]));
+        surface.attach(Some(&wl_buf), -hotspot.0, -hotspot.1);
+        surface.damage_buffer(0, 0, width, height);
+        Self { surface }
+    }
+}
+
+impl Drop for DragIcon {
+    fn drop(&mut self) {
+        self.surface.destroy();
+    }
+}
+
+/// A drag to start from one of our surfaces
+pub struct DragSource {
+    device: DataDevice,
+    origin: WlSurface,
+    mime_types: Vec<String>,
+    actions: DndAction,
+    icon: Option<DragIcon>,
+    on_send: Option<Box<dyn SendCb>>,
+    on_event: Option<Box<dyn DragCb>>,
+}
+
+impl std::fmt::Debug for DragSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct(std::any::type_name::<Self>())
+            .field("origin", &self.origin)
+            .field("mime_types", &self.mime_types)
+            .field("actions", &self.actions)
+            .field("icon", &self.icon)
+            .field("on_send", &"Cool closure")
+            .field("on_event", &"Cool closure")
+            .finish()
+    }
+}
+
+impl DragSource {
+    pub fn new(device: DataDevice, origin: &WlSurface, mime_types: &[&str], actions: DndAction) -> Self {
+        Self {
+            device,
+            origin: origin.clone(),
+            mime_types: mime_types.iter().map(|mime_type| mime_type.to_string()).collect(),
+            actions,
+            icon: None,
+            on_send: None,
+            on_event: None,
+        }
+    }
+    pub fn icon(mut self, icon: DragIcon) -> Self {
+        self.icon = Some(icon);
+        self
+    }
+    /// Writes the data once the target reads it
+    pub fn on_send(mut self, on_send: impl SendCb + 'static) -> Self {
+        self.on_send = Some(Box::new(on_send));
+        self
+    }
+    pub fn on_event(mut self, on_event: impl DragCb + 'static) -> Self {
+        self.on_event = Some(Box::new(on_event));
+        self
+    }
+    /// `serial` is the one of the button press that started the drag
+    pub fn start(self, serial: u32) {
+        let Self { device, origin, mime_types, actions, icon, on_send, on_event } = self;
+        let inner = device.inner.borrow();
+        let source = inner.manager.create_data_source();
+        let offered: Vec<&str> = mime_types.iter().map(String::as_str).collect();
+        for mime_type in with_aliases(&offered) {
+            source.offer(mime_type);
+        }
+        if source.as_ref().version() >= 3 {
+            source.set_actions(actions);
+        }
+        inner.device.start_drag(Some(&source), &origin, icon.as_ref().map(|icon| &**icon.surface), serial);
+        if let Some(icon) = &icon {
+            icon.surface.commit();
+        }
+
+        let emit = move |evt| {
+            if let Some(on_event) = &on_event {
+                on_event(evt)
+            }
+        };
+        let mut action = DndAction::None;
+        // The icon goes away with the drag
+        let mut icon = icon;
+        source.quick_assign(move |source, evt, _| match evt {
+            wl_data_source::Event::Target { mime_type } => {
+                let mime_type = mime_type
+                    .and_then(|mime_type| offered_mime_type(&mime_types, &mime_type).map(str::to_owned));
+                emit(DragSourceEvent::Target(mime_type))
+            }
+            wl_data_source::Event::Send { mime_type, fd } => {
+                let file = file_from_fd(fd);
+                if let (Some(mime_type), Some(on_send)) = (offered_mime_type(&mime_types, &mime_type), &on_send) {
+                    on_send(mime_type, file)
+                }
+            }
+            wl_data_source::Event::Action { dnd_action } => {
+                action = dnd_action;
+                emit(DragSourceEvent::Action(dnd_action))
+            }
+            wl_data_source::Event::DndDropPerformed => emit(DragSourceEvent::DropPerformed),
+            wl_data_source::Event::DndFinished => {
+                icon.take();
+                source.destroy();
+                emit(DragSourceEvent::Finished(action))
+            }
+            wl_data_source::Event::Cancelled => {
+                icon.take();
+                source.destroy();
+                emit(DragSourceEvent::Cancelled)
+            }
+            _ => (),
+        });
+    }
+}
@@ -0,0 +1,158 @@
+use std::cell::RefCell;
+use std::io;
+use std::rc::Rc;
+
+use wayland_client::protocol::wl_seat::WlSeat;
+use wayland_client::{Display, Main};
+use wayland_protocols::unstable::primary_selection::v1::client::{
+    zwp_primary_selection_device_manager_v1::ZwpPrimarySelectionDeviceManagerV1,
+    zwp_primary_selection_device_v1::{self, ZwpPrimarySelectionDeviceV1},
+    zwp_primary_selection_offer_v1::{self, ZwpPrimarySelectionOfferV1},
+    zwp_primary_selection_source_v1::{self, ZwpPrimarySelectionSourceV1},
+};
+
+use crate::prelude::*;
+
+use super::{
+    file_from_fd, offered_mime_type, pick_mime_type, with_aliases, DataRead, LocalSource, SendCb, Selection,
+};
+
+type OfferedMimeTypes = RefCell<Vec<String>>;
+
+fn offer_mime_types(offer: &ZwpPrimarySelectionOfferV1) -> Vec<String> {
+    offer
+        .as_ref()
+        .user_data()
+        .get::<OfferedMimeTypes>()
+        .map(|mime_types| mime_types.borrow().clone())
+        .unwrap_or_default()
+}
+
+struct PrimarySelectionInner {
+    manager: Main<ZwpPrimarySelectionDeviceManagerV1>,
+    device: Main<ZwpPrimarySelectionDeviceV1>,
+    display: Display,
+    selection: Option<ZwpPrimarySelectionOfferV1>,
+    selection_source: Option<LocalSource<ZwpPrimarySelectionSourceV1>>,
+}
+
+impl PrimarySelectionInner {
+    fn handle_event(&mut self, evt: zwp_primary_selection_device_v1::Event) {
+        match evt {
+            zwp_primary_selection_device_v1::Event::DataOffer { offer } => {
+                offer.as_ref().user_data().set(OfferedMimeTypes::default);
+                offer.quick_assign(|offer, evt, _| {
+                    if let zwp_primary_selection_offer_v1::Event::Offer { mime_type } = evt {
+                        if let Some(mime_types) = offer.as_ref().user_data().get::<OfferedMimeTypes>() {
+                            mime_types.borrow_mut().push(mime_type);
+                        }
+                    }
+                });
+            }
+            zwp_primary_selection_device_v1::Event::Selection { id } => {
+                if let Some(old) = self.selection.take() {
+                    old.destroy();
+                }
+                self.selection = id;
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Text selected anywhere, pasted with a middle click. Works like the [`Clipboard`](super::Clipboard).
+#[derive(Clone)]
+pub struct PrimarySelection {
+    inner: RcCell<PrimarySelectionInner>,
+}
+
+impl std::fmt::Debug for PrimarySelection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let inner = self.inner.borrow();
+        f.debug_struct(std::any::type_name::<Self>())
+            .field("device", &inner.device)
+            .field("selection", &inner.selection)
+            .finish()
+    }
+}
+
+impl PrimarySelection {
+    pub fn new(manager: &Main<ZwpPrimarySelectionDeviceManagerV1>, seat: &WlSeat, display: &Display) -> Self {
+        let device = manager.get_device(seat);
+        let this = Self {
+            inner: PrimarySelectionInner {
+                manager: manager.clone(),
+                device: device.clone(),
+                display: display.clone(),
+                selection: None,
+                selection_source: None,
+            }
+            .into(),
+        };
+        device.quick_assign({
+            let this = this.clone();
+            move |_, evt, _| this.inner.borrow_mut().handle_event(evt)
+        });
+        this
+    }
+}
+
+impl Selection for PrimarySelection {
+    fn set_selection(&self, serial: u32, mime_types: &[&str], on_send: impl SendCb + 'static) {
+        let mut inner = self.inner.borrow_mut();
+        let source = inner.manager.create_source();
+        for mime_type in with_aliases(mime_types) {
+            source.offer(mime_type);
+        }
+        let offered: Vec<String> = mime_types.iter().map(|mime_type| mime_type.to_string()).collect();
+        let on_send: Rc<dyn SendCb> = Rc::new(on_send);
+        source.quick_assign({
+            let this = self.clone();
+            let offered = offered.clone();
+            let on_send = on_send.clone();
+            move |source, evt, _| match evt {
+                zwp_primary_selection_source_v1::Event::Send { mime_type, fd } => {
+                    let file = file_from_fd(fd);
+                    if let Some(mime_type) = offered_mime_type(&offered, &mime_type) {
+                        on_send(mime_type, file)
+                    }
+                }
+                zwp_primary_selection_source_v1::Event::Cancelled => {
+                    let mut inner = this.inner.borrow_mut();
+                    if inner.selection_source.as_ref().is_some_and(|local| local.source == **source) {
+                        inner.selection_source = None;
+                    }
+                    source.destroy();
+                }
+                _ => (),
+            }
+        });
+        inner.device.set_selection(Some(&source), serial);
+        inner.selection_source = Some(LocalSource {
+            source: (**source).clone(),
+            mime_types: offered,
+            on_send,
+        });
+    }
+    fn clear_selection(&self, serial: u32) {
+        let mut inner = self.inner.borrow_mut();
+        inner.device.set_selection(None, serial);
+        inner.selection_source = None;
+    }
+    fn mime_types(&self) -> Vec<String> {
+        let inner = self.inner.borrow();
+        if let Some(local) = &inner.selection_source {
+            return with_aliases(&local.mime_types.iter().map(String::as_str).collect::<Vec<_>>());
+        }
+        inner.selection.as_ref().map(offer_mime_types).unwrap_or_default()
+    }
+    fn read(&self, mime_type: &str) -> Option<io::Result<DataRead>> {
+        let inner = self.inner.borrow();
+        if let Some(local) = &inner.selection_source {
+            return local.read(mime_type);
+        }
+        let offer = inner.selection.as_ref()?;
+        let mime_type = pick_mime_type(&offer_mime_types(offer), mime_type)?;
+        Some(DataRead::start(&inner.display, |fd| offer.receive(mime_type, fd)))
+    }
+}
@@ -0,0 +1,113 @@
+/// An axis aligned rectangle in surface coordinates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Rect {
+    pub const fn new(x: i32, y: i32, width: i32, height: i32) -> Self {
+        Self { x, y, width, height }
+    }
+    pub fn contains(&self, x: f64, y: f64) -> bool {
+        x >= self.x as f64
+            && y >= self.y as f64
+            && x < (self.x + self.width) as f64
+            && y < (self.y + self.height) as f64
+    }
+    /// The rectangle shrunk by `insets`, never smaller than empty
+    pub fn inset(&self, insets: Insets) -> Self {
+        Self {
+            x: self.x + insets.left,
+            y: self.y + insets.top,
+            width: (self.width - insets.horizontal()).max(0),
+            height: (self.height - insets.vertical()).max(0),
+        }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.width <= 0 || self.height <= 0
+    }
+    /// Whether they share some area, touching edges don't count
+    pub fn intersects(&self, other: Rect) -> bool {
+        !self.intersect(other).is_empty()
+    }
+    /// The smallest rectangle containing both. Empty ones are ignored.
+    pub fn union(&self, other: Rect) -> Self {
+        if self.is_empty() {
+            return other;
+        }
+        if other.is_empty() {
+            return *self;
+        }
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        let right = (self.x + self.width).max(other.x + other.width);
+        let bottom = (self.y + self.height).max(other.y + other.height);
+        Self::new(x, y, right - x, bottom - y)
+    }
+    /// The area covered by both, empty if they don't overlap
+    pub fn intersect(&self, other: Rect) -> Self {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = (self.x + self.width).min(other.x + other.width);
+        let bottom = (self.y + self.height).min(other.y + other.height);
+        Self::new(x, y, (right - x).max(0), (bottom - y).max(0))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Size {
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Size {
+    pub const MAX: Self = Self::new(i32::MAX, i32::MAX);
+
+    pub const fn new(width: i32, height: i32) -> Self {
+        Self { width, height }
+    }
+    /// Clamps each side between `min` and `max`, `min` winning if they cross
+    pub fn clamp(self, min: Size, max: Size) -> Self {
+        Self {
+            width: self.width.min(max.width).max(min.width),
+            height: self.height.min(max.height).max(min.height),
+        }
+    }
+    /// Both sides times `scale`, rounded to whole pixels
+    pub fn scale(self, scale: f64) -> Self {
+        Self {
+            width: (self.width as f64 * scale).round() as i32,
+            height: (self.height as f64 * scale).round() as i32,
+        }
+    }
+}
+
+/// Space around the sides of a rectangle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Insets {
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+    pub left: i32,
+}
+
+impl Insets {
+    pub const fn new(top: i32, right: i32, bottom: i32, left: i32) -> Self {
+        Self { top, right, bottom, left }
+    }
+    pub const fn uniform(inset: i32) -> Self {
+        Self::new(inset, inset, inset, inset)
+    }
+    pub const fn symmetric(vertical: i32, horizontal: i32) -> Self {
+        Self::new(vertical, horizontal, vertical, horizontal)
+    }
+    pub const fn horizontal(&self) -> i32 {
+        self.left + self.right
+    }
+    pub const fn vertical(&self) -> i32 {
+        self.top + self.bottom
+    }
+}
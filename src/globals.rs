@@ -1,13 +1,30 @@
+use std::path::PathBuf;
 use std::rc::Rc;
 
+use smithay_client_toolkit::reexports::calloop::LoopHandle;
 use smithay_client_toolkit::shm::AutoMemPool;
 use wayland_client::{
     protocol::{
-        wl_compositor::WlCompositor, wl_shm::WlShm, wl_seat::WlSeat, wl_subcompositor::WlSubcompositor, wl_pointer::WlPointer,
-    }, GlobalError, GlobalManager, Interface, Main, Proxy, Display,
+        wl_compositor::WlCompositor, wl_shm::{self, WlShm}, wl_seat::WlSeat, wl_subcompositor::WlSubcompositor, wl_pointer::WlPointer,
+    wl_display::WlDisplay,
+    }, Attached, GlobalError, GlobalManager, Interface, Main, Proxy, Display,
 };
+use wayland_protocols::unstable::idle_inhibit::v1::client::zwp_idle_inhibit_manager_v1::ZwpIdleInhibitManagerV1;
+use wayland_protocols::unstable::keyboard_shortcuts_inhibit::v1::client::zwp_keyboard_shortcuts_inhibit_manager_v1::ZwpKeyboardShortcutsInhibitManagerV1;
+use wayland_protocols::unstable::pointer_constraints::v1::client::zwp_pointer_constraints_v1::ZwpPointerConstraintsV1;
+use wayland_protocols::unstable::relative_pointer::v1::client::zwp_relative_pointer_manager_v1::ZwpRelativePointerManagerV1;
+use wayland_protocols::unstable::tablet::v2::client::zwp_tablet_manager_v2::ZwpTabletManagerV2;
+use wayland_protocols::unstable::text_input::v3::client::zwp_text_input_manager_v3::ZwpTextInputManagerV3;
 
+use crate::protocols::pointer_gestures::zwp_pointer_gestures_v1::ZwpPointerGesturesV1;
+
+use crate::data_device::{Clipboard, DataDevice, PrimarySelection};
+use crate::icons::IconLookup;
+use crate::keyboard::Keyboard;
+use crate::config::Config;
 use crate::prelude::*;
+use crate::renderer::{ImageCache, PixelFormat};
+use crate::widget::Theme;
 
 use crate::window::WindowBackend;
 
@@ -17,9 +34,26 @@ pub struct GlobalsInner<B: BackendGlobals> {
     pub wl_compositor: Main<WlCompositor>,
     pub wl_seat: Main<WlSeat>,
     pub wl_shm: Main<WlShm>,
+    /// Advertised by `wl_shm` once bound
+    pub shm_formats: RcCell<Vec<wl_shm::Format>>,
+    /// Shared by everything showing images, like wallpapers and icons
+    pub image_cache: ImageCache,
+    /// Follows the icon theme of the config, see [`GlobalsHandle::icon`]
+    pub icon_lookup: IconLookup,
     pub wl_subcompositor: Main<WlSubcompositor>,
     pub display: Display,
     // pub wl_pointer: Main<WlPointer>,
+    pub pointer_constraints: Option<Main<ZwpPointerConstraintsV1>>,
+    pub relative_pointer_manager: Option<Main<ZwpRelativePointerManagerV1>>,
+    pub pointer_gestures: Option<Main<ZwpPointerGesturesV1>>,
+    pub tablet_manager: Option<Main<ZwpTabletManagerV2>>,
+    pub text_input_manager: Option<Main<ZwpTextInputManagerV3>>,
+    pub data_device: Option<DataDevice>,
+    pub primary_selection: Option<PrimarySelection>,
+    pub idle_inhibit_manager: Option<Main<ZwpIdleInhibitManagerV1>>,
+    pub shortcuts_inhibit_manager: Option<Main<ZwpKeyboardShortcutsInhibitManagerV1>>,
+    pub keyboard: Keyboard,
+    pub config: Config,
 
     pub shm_pool: RcCell<AutoMemPool>,
     pub backend: Rc<B>,
@@ -27,9 +61,34 @@ pub struct GlobalsInner<B: BackendGlobals> {
 
 
 impl<B: BackendGlobals> GlobalsHandle<B> {
-    pub fn new(global_manager: GlobalManager, display: &Display) -> Self {
+    /// `attached_display` is `display` attached to the queue that gets dispatched, by the
+    /// event loop of `loop_handle`
+    pub fn new(
+        global_manager: GlobalManager,
+        display: &Display,
+        attached_display: &Attached<WlDisplay>,
+        loop_handle: LoopHandle<'static, ()>,
+    ) -> Self {
         let shm = global_manager.get::<WlShm>();
+        let shm_formats = RcCell::<Vec<wl_shm::Format>>::default();
+        shm.quick_assign({
+            let shm_formats = shm_formats.clone();
+            move |_, evt, _| {
+                if let wl_shm::Event::Format { format } = evt {
+                    shm_formats.borrow_mut().push(format);
+                }
+            }
+        });
         let wl_seat: Main<WlSeat> = global_manager.get();
+        let data_device = global_manager
+            .instantiate_current()
+            .ok()
+            .map(|manager| DataDevice::new(&manager, &wl_seat, display));
+        let primary_selection = global_manager
+            .instantiate_current()
+            .ok()
+            .map(|manager| PrimarySelection::new(&manager, &wl_seat, display));
+        let config = Config::load();
         let inner = GlobalsInner {
             display: display.clone(),
             wl_compositor: global_manager.get(),
@@ -37,6 +96,21 @@ impl<B: BackendGlobals> GlobalsHandle<B> {
             wl_seat,
             wl_subcompositor: global_manager.get(),
             wl_shm: shm.clone(),
+            shm_formats,
+            image_cache: ImageCache::default(),
+            icon_lookup: IconLookup::new(&config.icon_theme()),
+            pointer_constraints: global_manager.instantiate_current().ok(),
+            relative_pointer_manager: global_manager.instantiate_current().ok(),
+            // Swipe and pinch only need version 1, hold gestures are checked for on use
+            pointer_gestures: global_manager.instantiate_range(1, 3).ok(),
+            tablet_manager: global_manager.instantiate_current().ok(),
+            text_input_manager: global_manager.instantiate_current().ok(),
+            data_device,
+            primary_selection,
+            idle_inhibit_manager: global_manager.instantiate_current().ok(),
+            shortcuts_inhibit_manager: global_manager.instantiate_current().ok(),
+            keyboard: Keyboard::new(attached_display, loop_handle),
+            config,
             backend: B::new(&global_manager),
             shm_pool: RcCell::new(AutoMemPool::new(shm.into()).unwrap()),
             global_manager,
@@ -44,9 +118,32 @@ impl<B: BackendGlobals> GlobalsHandle<B> {
         let inner = Rc::new(inner);
         Self { inner }
     }
+    /// Best format for drawing windows, fails if the compositor supports none we can draw.
+    /// Only known after a roundtrip.
+    pub fn pixel_format(&self) -> anyhow::Result<PixelFormat> {
+        PixelFormat::pick(&self.shm_formats.borrow())
+    }
+    /// Variant of the theme in use
+    pub fn theme(&self) -> Theme {
+        self.config.theme()
+    }
+    /// The file of icon `name` in the current icon theme that best fits `size` at `scale`,
+    /// ready for [`ImageCache`]
+    pub fn icon(&self, name: &str, size: i32, scale: i32) -> Option<PathBuf> {
+        self.icon_lookup.set_theme(&self.config.icon_theme());
+        self.icon_lookup.find(name, size, scale)
+    }
     pub fn new_window(&self) -> B::Window {
         B::Window::new(self.clone())
     }
+    /// `None` if the compositor has no `wl_data_device_manager`
+    pub fn clipboard(&self) -> Option<Clipboard> {
+        self.data_device.as_ref().map(DataDevice::clipboard)
+    }
+    /// `None` if the compositor has no primary selection
+    pub fn primary_selection(&self) -> Option<PrimarySelection> {
+        self.primary_selection.clone()
+    }
 }
 
 pub trait BackendGlobals {
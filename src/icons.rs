@@ -0,0 +1,474 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use anyhow::{bail, Context};
+
+use crate::prelude::*;
+
+/// Theme every other one ends up inheriting from
+const FALLBACK_THEME: &str = "hicolor";
+/// The formats the renderer can draw, in order of preference. The spec also allows XPM.
+const EXTENSIONS: [&str; 2] = ["png", "svg"];
+
+/// Groups of `index.theme`, each a map of its keys. Localized keys like `Name[de]` are kept
+/// as they are.
+type Groups = HashMap<String, HashMap<String, String>>;
+
+fn parse_groups(text: &str) -> anyhow::Result<Groups> {
+    let mut groups = Groups::new();
+    let mut group = None;
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+            group = Some(groups.entry(name.to_owned()).or_default());
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            bail!("Line {} is neither a group nor a key: {line}", i + 1);
+        };
+        let Some(group) = &mut group else {
+            bail!("Key {} on line {} is outside of any group", key.trim(), i + 1);
+        };
+        group.insert(key.trim().to_owned(), value.trim().to_owned());
+    }
+    Ok(groups)
+}
+
+/// Comma separated list, as used by `Inherits` and `Directories`
+fn parse_list(value: Option<&String>) -> Vec<String> {
+    let Some(value) = value else {
+        return Vec::new();
+    };
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DirType {
+    /// Icons of exactly `size`
+    Fixed,
+    /// Icons fit for any size between `min_size` and `max_size`, usually SVGs
+    Scalable,
+    /// Icons fit for sizes up to `threshold` away from `size`
+    Threshold,
+}
+
+/// A directory of a theme and the sizes of the icons in it
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct IconDir {
+    /// Relative to the theme's directory
+    path: String,
+    size: i32,
+    scale: i32,
+    kind: DirType,
+    min_size: i32,
+    max_size: i32,
+    threshold: i32,
+}
+
+impl IconDir {
+    fn parse(path: &str, keys: &HashMap<String, String>) -> anyhow::Result<Self> {
+        let int = |key: &str| -> anyhow::Result<Option<i32>> {
+            let Some(value) = keys.get(key) else {
+                return Ok(None);
+            };
+            let value = value.parse().with_context(|| format!("{key} isn't a number: {value}"))?;
+            Ok(Some(value))
+        };
+        let size = int("Size")?.context("Size is missing")?;
+        let kind = match keys.get("Type").map(String::as_str) {
+            Some("Fixed") => DirType::Fixed,
+            Some("Scalable") => DirType::Scalable,
+            Some("Threshold") | None => DirType::Threshold,
+            Some(other) => bail!("Unknown Type {other}"),
+        };
+        Ok(Self {
+            path: path.to_owned(),
+            size,
+            scale: int("Scale")?.unwrap_or(1),
+            kind,
+            min_size: int("MinSize")?.unwrap_or(size),
+            max_size: int("MaxSize")?.unwrap_or(size),
+            threshold: int("Threshold")?.unwrap_or(2),
+        })
+    }
+    /// Whether its icons are meant for `size` at `scale`
+    fn matches(&self, size: i32, scale: i32) -> bool {
+        if self.scale != scale {
+            return false;
+        }
+        match self.kind {
+            DirType::Fixed => self.size == size,
+            DirType::Scalable => (self.min_size..=self.max_size).contains(&size),
+            DirType::Threshold => (self.size - self.threshold..=self.size + self.threshold).contains(&size),
+        }
+    }
+    /// How far its icons are from `size` at `scale`, in buffer pixels
+    fn distance(&self, size: i32, scale: i32) -> i32 {
+        let wanted = size * scale;
+        let (min, max) = match self.kind {
+            DirType::Fixed => (self.size, self.size),
+            DirType::Scalable => (self.min_size, self.max_size),
+            DirType::Threshold => (self.size - self.threshold, self.size + self.threshold),
+        };
+        let (min, max) = (min * self.scale, max * self.scale);
+        if wanted < min {
+            min - wanted
+        } else if wanted > max {
+            wanted - max
+        } else {
+            0
+        }
+    }
+}
+
+#[derive(Debug)]
+struct IconTheme {
+    /// Directories of the theme, one per base directory it is in
+    roots: Vec<PathBuf>,
+    dirs: Vec<IconDir>,
+    parents: Vec<String>,
+}
+
+impl IconTheme {
+    fn parse(index: &str, roots: Vec<PathBuf>) -> anyhow::Result<Self> {
+        let groups = parse_groups(index)?;
+        let theme = groups.get("Icon Theme").context("[Icon Theme] is missing")?;
+        let name = theme.get("Name").map_or("without a name", String::as_str);
+        let mut dirs = parse_list(theme.get("Directories"));
+        dirs.extend(parse_list(theme.get("ScaledDirectories")));
+        let dirs = dirs
+            .iter()
+            .filter_map(|dir| {
+                // Listed directories without a group are skipped, as other implementations do
+                let keys = groups.get(dir)?;
+                // As are broken ones, the rest of the theme still works
+                IconDir::parse(dir, keys)
+                    .map_err(|e| eprintln!("Skipping [{dir}] of the icon theme {name}: {e:#}"))
+                    .ok()
+            })
+            .collect();
+        Ok(Self {
+            roots,
+            dirs,
+            parents: parse_list(theme.get("Inherits")),
+        })
+    }
+    /// Icon `name` in a directory meant for `size` at `scale`, or else the closest size it has
+    fn lookup(&self, name: &str, size: i32, scale: i32) -> Option<PathBuf> {
+        let files = |dir: &IconDir| {
+            let path = dir.path.clone();
+            self.roots.iter().flat_map(move |root| {
+                let dir = root.join(&path);
+                EXTENSIONS.iter().map(move |ext| dir.join(format!("{name}.{ext}")))
+            })
+        };
+        let matching = self.dirs.iter().filter(|dir| dir.matches(size, scale));
+        if let Some(file) = matching.flat_map(files).find(|file| file.is_file()) {
+            return Some(file);
+        }
+        let mut closest = None;
+        let mut min_distance = i32::MAX;
+        for dir in &self.dirs {
+            let distance = dir.distance(size, scale);
+            if distance >= min_distance {
+                continue;
+            }
+            if let Some(file) = files(dir).find(|file| file.is_file()) {
+                closest = Some(file);
+                min_distance = distance;
+            }
+        }
+        closest
+    }
+}
+
+struct IconLookupInner {
+    theme: String,
+    base_dirs: Vec<PathBuf>,
+    /// Loaded on first use, `None` for themes that aren't installed or are broken
+    themes: HashMap<String, Option<Rc<IconTheme>>>,
+    found: HashMap<(String, i32, i32), Option<PathBuf>>,
+}
+
+/// Turns icon names into files, following the freedesktop icon theme spec
+#[derive(Clone)]
+pub struct IconLookup {
+    inner: RcCell<IconLookupInner>,
+}
+
+impl std::fmt::Debug for IconLookup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let inner = self.inner.borrow();
+        f.debug_struct(std::any::type_name::<Self>())
+            .field("theme", &inner.theme)
+            .field("base_dirs", &inner.base_dirs)
+            .finish()
+    }
+}
+
+impl IconLookup {
+    /// Looks in `~/.icons`, then the `icons` directories of `$XDG_DATA_HOME` and
+    /// `$XDG_DATA_DIRS`, then `/usr/share/pixmaps`
+    pub fn new(theme: &str) -> Self {
+        let home = std::env::var_os("HOME").map(PathBuf::from);
+        let data_home = std::env::var_os("XDG_DATA_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| Some(home.as_ref()?.join(".local").join("share")));
+        let data_dirs = std::env::var("XDG_DATA_DIRS")
+            .ok()
+            .filter(|dirs| !dirs.is_empty())
+            .unwrap_or_else(|| "/usr/local/share:/usr/share".to_owned());
+        let mut base_dirs: Vec<PathBuf> = home.map(|home| home.join(".icons")).into_iter().collect();
+        base_dirs.extend(data_home.map(|dir| dir.join("icons")));
+        let data_dirs = data_dirs.split(':').filter(|dir| !dir.is_empty());
+        base_dirs.extend(data_dirs.map(|dir| Path::new(dir).join("icons")));
+        base_dirs.push(PathBuf::from("/usr/share/pixmaps"));
+        Self::with_base_dirs(theme, base_dirs)
+    }
+    /// Looks in `base_dirs` in order. Themes are directories in them, icons directly in them
+    /// are the last resort.
+    pub fn with_base_dirs(theme: &str, base_dirs: Vec<PathBuf>) -> Self {
+        Self {
+            inner: IconLookupInner {
+                theme: theme.to_owned(),
+                base_dirs,
+                themes: HashMap::new(),
+                found: HashMap::new(),
+            }
+            .into(),
+        }
+    }
+    /// The file of icon `name`, without extension, that best fits `size` at `scale`. It comes
+    /// from the theme, then the ones it inherits, then `hicolor`, then the base directories.
+    pub fn find(&self, name: &str, size: i32, scale: i32) -> Option<PathBuf> {
+        let key = (name.to_owned(), size, scale);
+        if let Some(found) = self.inner.borrow().found.get(&key) {
+            return found.clone();
+        }
+        let theme = self.inner.borrow().theme.clone();
+        let mut visited = HashSet::new();
+        let found = self
+            .find_in(&theme, name, size, scale, &mut visited)
+            .or_else(|| self.find_in(FALLBACK_THEME, name, size, scale, &mut visited))
+            .or_else(|| self.find_loose(name));
+        self.inner.borrow_mut().found.insert(key, found.clone());
+        found
+    }
+    /// Looks in `theme` from now on, keeping the themes already loaded
+    pub fn set_theme(&self, theme: &str) {
+        let mut inner = self.inner.borrow_mut();
+        if inner.theme != theme {
+            inner.theme = theme.to_owned();
+            inner.found.clear();
+        }
+    }
+    /// Forgets what was found and loaded, to see icons installed since
+    pub fn clear(&self) {
+        let mut inner = self.inner.borrow_mut();
+        inner.themes.clear();
+        inner.found.clear();
+    }
+    /// Looks in `theme`, then the ones it inherits, depth first. `visited` guards against
+    /// themes inheriting in a loop.
+    fn find_in(
+        &self,
+        theme: &str,
+        name: &str,
+        size: i32,
+        scale: i32,
+        visited: &mut HashSet<String>,
+    ) -> Option<PathBuf> {
+        if !visited.insert(theme.to_owned()) {
+            return None;
+        }
+        let theme = self.theme(theme)?;
+        if let Some(file) = theme.lookup(name, size, scale) {
+            return Some(file);
+        }
+        theme
+            .parents
+            .iter()
+            .find_map(|parent| self.find_in(parent, name, size, scale, visited))
+    }
+    /// Icon files right in the base directories, like those of `/usr/share/pixmaps`
+    fn find_loose(&self, name: &str) -> Option<PathBuf> {
+        let inner = self.inner.borrow();
+        inner
+            .base_dirs
+            .iter()
+            .flat_map(|dir| EXTENSIONS.iter().map(move |ext| dir.join(format!("{name}.{ext}"))))
+            .find(|file| file.is_file())
+    }
+    /// `name` loaded from the first `index.theme` of it, a broken one is reported and skipped
+    fn theme(&self, name: &str) -> Option<Rc<IconTheme>> {
+        if let Some(theme) = self.inner.borrow().themes.get(name) {
+            return theme.clone();
+        }
+        let roots: Vec<PathBuf> = {
+            let inner = self.inner.borrow();
+            inner.base_dirs.iter().map(|dir| dir.join(name)).filter(|dir| dir.is_dir()).collect()
+        };
+        let theme = roots
+            .iter()
+            .map(|root| root.join("index.theme"))
+            .find(|index| index.is_file())
+            .and_then(|index| {
+                let text = std::fs::read_to_string(&index);
+                let theme = text.map_err(Into::into).and_then(|text| IconTheme::parse(&text, roots.clone()));
+                theme
+                    .map_err(|e| eprintln!("Failed to load the icon theme {}: {e:#}", index.display()))
+                    .ok()
+            })
+            .map(Rc::new);
+        self.inner.borrow_mut().themes.insert(name.to_owned(), theme.clone());
+        theme
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HICOLOR: &str = "
+[Icon Theme]
+Name=Hicolor
+Directories=48x48/apps
+
+[48x48/apps]
+Size=48
+Type=Threshold
+";
+    const SIZED: &str = "
+[Icon Theme]
+Name=Sized
+Inherits=Parent
+Directories=16x16/apps,48x48/apps,broken
+
+[16x16/apps]
+Size=16
+Type=Fixed
+
+[48x48/apps]
+Size=48
+Type=Fixed
+
+[broken]
+Type=Fixed
+";
+    const PARENT: &str = "
+[Icon Theme]
+Name=Parent
+Inherits=Sized
+Directories=apps
+
+[apps]
+Size=32
+Type=Scalable
+MinSize=8
+MaxSize=512
+";
+
+    /// Base directories in a temporary directory, removed when dropped
+    struct Fixture {
+        root: PathBuf,
+    }
+
+    impl Fixture {
+        fn new(test: &str) -> Self {
+            let root = std::env::temp_dir().join(format!("icons-{}-{test}", std::process::id()));
+            let fixture = Self { root };
+            fixture.theme("hicolor", HICOLOR);
+            fixture.theme("Sized", SIZED);
+            fixture.theme("Parent", PARENT);
+            fixture
+        }
+        fn theme(&self, name: &str, index: &str) {
+            self.file(&format!("icons/{name}/index.theme"), index);
+        }
+        fn file(&self, path: &str, contents: &str) -> PathBuf {
+            let path = self.root.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, contents).unwrap();
+            path
+        }
+        fn icon(&self, path: &str) -> PathBuf {
+            self.file(&format!("icons/{path}"), "")
+        }
+        fn lookup(&self, theme: &str) -> IconLookup {
+            IconLookup::with_base_dirs(theme, vec![self.root.join("icons"), self.root.join("pixmaps")])
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.root);
+        }
+    }
+
+    #[test]
+    fn exact_size() {
+        let fixture = Fixture::new("exact_size");
+        let small = fixture.icon("Sized/16x16/apps/term.png");
+        let large = fixture.icon("Sized/48x48/apps/term.svg");
+        let lookup = fixture.lookup("Sized");
+        assert_eq!(lookup.find("term", 16, 1), Some(small));
+        assert_eq!(lookup.find("term", 48, 1), Some(large));
+    }
+
+    #[test]
+    fn closest_size() {
+        let fixture = Fixture::new("closest_size");
+        let small = fixture.icon("Sized/16x16/apps/term.png");
+        let large = fixture.icon("Sized/48x48/apps/term.png");
+        let lookup = fixture.lookup("Sized");
+        assert_eq!(lookup.find("term", 40, 1), Some(large.clone()));
+        assert_eq!(lookup.find("term", 20, 1), Some(small));
+        // 24 at scale 2 is 48 buffer pixels
+        assert_eq!(lookup.find("term", 24, 2), Some(large));
+    }
+
+    #[test]
+    fn inherited() {
+        let fixture = Fixture::new("inherited");
+        let parent = fixture.icon("Parent/apps/editor.svg");
+        let hicolor = fixture.icon("hicolor/48x48/apps/browser.png");
+        let lookup = fixture.lookup("Sized");
+        assert_eq!(lookup.find("editor", 48, 1), Some(parent));
+        assert_eq!(lookup.find("browser", 48, 1), Some(hicolor));
+    }
+
+    #[test]
+    fn inheritance_loop() {
+        let fixture = Fixture::new("inheritance_loop");
+        let hicolor = fixture.icon("hicolor/48x48/apps/browser.png");
+        let lookup = fixture.lookup("Parent");
+        assert_eq!(lookup.find("missing", 48, 1), None);
+        assert_eq!(lookup.find("browser", 48, 1), Some(hicolor));
+    }
+
+    #[test]
+    fn loose_pixmaps() {
+        let fixture = Fixture::new("loose_pixmaps");
+        let pixmap = fixture.file("pixmaps/legacy.png", "");
+        let lookup = fixture.lookup("Sized");
+        assert_eq!(lookup.find("legacy", 48, 1), Some(pixmap));
+    }
+
+    #[test]
+    fn broken_directory() {
+        let theme = IconTheme::parse(SIZED, Vec::new()).unwrap();
+        let dirs: Vec<_> = theme.dirs.iter().map(|dir| dir.path.as_str()).collect();
+        assert_eq!(dirs, ["16x16/apps", "48x48/apps"]);
+        assert_eq!(theme.parents, ["Parent"]);
+    }
+}
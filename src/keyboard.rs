@@ -0,0 +1,219 @@
+use std::rc::Rc;
+
+use smithay_client_toolkit::environment::MultiGlobalHandler;
+use smithay_client_toolkit::reexports::calloop::LoopHandle;
+use smithay_client_toolkit::seat::keyboard::{self, KeyState, RepeatKind};
+use smithay_client_toolkit::seat::{SeatData, SeatHandler, SeatHandling, SeatListener};
+use wayland_client::protocol::{
+    wl_display::WlDisplay,
+    wl_keyboard::WlKeyboard,
+    wl_registry,
+    wl_seat::WlSeat,
+    wl_surface::WlSurface,
+};
+use wayland_client::{Attached, Interface};
+
+pub use smithay_client_toolkit::seat::keyboard::{keysyms, ModifiersState};
+
+use crate::prelude::*;
+
+/// A key going down or up, translated through the seat's keymap. Held keys are pressed
+/// again at the rate the compositor asks for.
+#[derive(Debug, Clone)]
+pub struct KeyEvent {
+    /// One of [`keysyms`]
+    pub keysym: u32,
+    /// Text the key types, only set on presses
+    pub utf8: Option<String>,
+    pub modifiers: ModifiersState,
+    pub pressed: bool,
+    pub serial: u32,
+    pub time: u32,
+}
+
+/// Sent to the surface having keyboard focus
+#[derive(Debug, Clone)]
+pub enum KeyboardEvent {
+    Enter { serial: u32 },
+    Leave,
+    Key(KeyEvent),
+}
+
+pub trait KeyboardCb = Fn(KeyboardEvent);
+
+struct KeyboardInner {
+    keyboards: Vec<(WlSeat, WlKeyboard)>,
+    modifiers: ModifiersState,
+    focus: Option<(WlKeyboard, WlSurface)>,
+    /// Of the last key press, repeats of the key are sent with it
+    key_serial: u32,
+    targets: Vec<(WlSurface, Rc<dyn KeyboardCb>)>,
+    listener: Option<SeatListener>,
+}
+
+impl KeyboardInner {
+    fn target(&self, surface: &WlSurface) -> Option<Rc<dyn KeyboardCb>> {
+        self.targets
+            .iter()
+            .find(|(target, _)| target == surface)
+            .map(|(_, cb)| cb.clone())
+    }
+    fn handle_event(&mut self, evt: keyboard::Event, wl_keyboard: WlKeyboard) -> Option<(Rc<dyn KeyboardCb>, KeyboardEvent)> {
+        match evt {
+            keyboard::Event::Enter { serial, surface, .. } => {
+                self.focus = Some((wl_keyboard, surface.clone()));
+                Some((self.target(&surface)?, KeyboardEvent::Enter { serial }))
+            }
+            keyboard::Event::Leave { surface, .. } => {
+                self.focus = None;
+                Some((self.target(&surface)?, KeyboardEvent::Leave))
+            }
+            keyboard::Event::Modifiers { modifiers } => {
+                self.modifiers = modifiers;
+                None
+            }
+            keyboard::Event::Key { serial, time, keysym, state, utf8, .. } => {
+                let (_, surface) = self.focus.as_ref()?;
+                let pressed = state == KeyState::Pressed;
+                if pressed {
+                    self.key_serial = serial;
+                }
+                let evt = KeyEvent {
+                    keysym,
+                    utf8,
+                    modifiers: self.modifiers,
+                    pressed,
+                    serial,
+                    time,
+                };
+                Some((self.target(surface)?, KeyboardEvent::Key(evt)))
+            }
+            keyboard::Event::Repeat { time, keysym, utf8, .. } => {
+                let (_, surface) = self.focus.as_ref()?;
+                let evt = KeyEvent {
+                    keysym,
+                    utf8,
+                    modifiers: self.modifiers,
+                    pressed: true,
+                    serial: self.key_serial,
+                    time,
+                };
+                Some((self.target(surface)?, KeyboardEvent::Key(evt)))
+            }
+        }
+    }
+    /// Forgets the keyboard of `seat`, returning the `Leave` to send if it had focus
+    fn remove_keyboard(&mut self, seat: &WlSeat) -> Option<(Rc<dyn KeyboardCb>, KeyboardEvent)> {
+        let index = self.keyboards.iter().position(|(keyboard_seat, _)| keyboard_seat == seat)?;
+        let (_, wl_keyboard) = self.keyboards.remove(index);
+        if wl_keyboard.as_ref().version() >= 3 {
+            wl_keyboard.release();
+        }
+        let had_focus = self.focus.as_ref().is_some_and(|(focused, _)| *focused == wl_keyboard);
+        if !had_focus {
+            return None;
+        }
+        let (_, surface) = self.focus.take()?;
+        Some((self.target(&surface)?, KeyboardEvent::Leave))
+    }
+}
+
+/// Keyboards of all seats. Key events go to the handler of the surface having focus.
+#[derive(Clone)]
+pub struct Keyboard {
+    inner: RcCell<KeyboardInner>,
+    // Separate from the rest, as removing a seat calls back into us
+    seats: RcCell<SeatHandler>,
+    /// Runs the timers of key repeats
+    loop_handle: LoopHandle<'static, ()>,
+}
+
+impl std::fmt::Debug for Keyboard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let inner = self.inner.borrow();
+        f.debug_struct(std::any::type_name::<Self>())
+            .field("seats", &*self.seats.borrow())
+            .field("keyboards", &inner.keyboards)
+            .field("modifiers", &inner.modifiers)
+            .field("focus", &inner.focus)
+            .finish()
+    }
+}
+
+impl Keyboard {
+    /// Binds the seats again through a registry of its own, as sctk only maps keymaps
+    /// for seats it tracks.
+    pub fn new(display: &Attached<WlDisplay>, loop_handle: LoopHandle<'static, ()>) -> Self {
+        let this = Self {
+            inner: KeyboardInner {
+                keyboards: Vec::new(),
+                modifiers: ModifiersState::default(),
+                focus: None,
+                key_serial: 0,
+                targets: Vec::new(),
+                listener: None,
+            }
+            .into(),
+            seats: SeatHandler::new().into(),
+            loop_handle,
+        };
+        let listener = this.seats.borrow_mut().listen({
+            let this = this.clone();
+            move |seat, data, _| this.seat_changed(seat, data)
+        });
+        this.inner.borrow_mut().listener = Some(listener);
+
+        let registry = display.get_registry();
+        registry.quick_assign({
+            let this = this.clone();
+            move |registry, evt, ddata| match evt {
+                wl_registry::Event::Global { name, interface, version } if interface == WlSeat::NAME => {
+                    this.seats.borrow_mut().created((*registry).clone(), name, version, ddata)
+                }
+                wl_registry::Event::GlobalRemove { name } => this.seats.borrow_mut().removed(name, ddata),
+                _ => (),
+            }
+        });
+        this
+    }
+    fn seat_changed(&self, seat: Attached<WlSeat>, data: &SeatData) {
+        let has_keyboard = self.inner.borrow().keyboards.iter().any(|(keyboard_seat, _)| *keyboard_seat == *seat);
+        if data.has_keyboard && !data.defunct && !has_keyboard {
+            let loop_handle = self.loop_handle.clone();
+            let wl_keyboard = keyboard::map_keyboard_repeat(loop_handle, &seat, None, RepeatKind::System, {
+                let this = self.clone();
+                move |evt, wl_keyboard, _| {
+                    let handled = this.inner.borrow_mut().handle_event(evt, wl_keyboard);
+                    if let Some((target, evt)) = handled {
+                        target(evt);
+                    }
+                }
+            });
+            match wl_keyboard {
+                Ok(wl_keyboard) => self.inner.borrow_mut().keyboards.push(((*seat).clone(), wl_keyboard)),
+                Err(e) => eprintln!("Failed to map keyboard of seat {}: {e:?}", data.name),
+            }
+        } else if (!data.has_keyboard || data.defunct) && has_keyboard {
+            let left = self.inner.borrow_mut().remove_keyboard(&seat);
+            if let Some((target, evt)) = left {
+                target(evt);
+            }
+        }
+    }
+    /// Sends the key events of `surface` to `on_key`, replacing its previous handler
+    pub fn set_target(&self, surface: &WlSurface, on_key: impl KeyboardCb + 'static) {
+        let mut inner = self.inner.borrow_mut();
+        inner.targets.retain(|(target, _)| target != surface);
+        inner.targets.push((surface.clone(), Rc::new(on_key)));
+    }
+    pub fn remove_target(&self, surface: &WlSurface) {
+        self.inner.borrow_mut().targets.retain(|(target, _)| target != surface);
+    }
+    /// Surface having keyboard focus, if it is one of ours
+    pub fn focus(&self) -> Option<WlSurface> {
+        self.inner.borrow().focus.as_ref().map(|(_, surface)| surface.clone())
+    }
+    pub fn modifiers(&self) -> ModifiersState {
+        self.inner.borrow().modifiers
+    }
+}
@@ -1,15 +1,27 @@
 #![feature(trait_alias)]
+use std::io;
+
 use globals::GlobalsHandle;
 
 
+use smithay_client_toolkit::reexports::calloop::generic::Generic;
+use smithay_client_toolkit::reexports::calloop::{EventLoop, Interest, Mode, PostAction};
+use smithay_client_toolkit::WaylandSource;
 use wayland_client::GlobalManager;
 use window::{xdg_shell::{XdgGlobals}, WindowBackend};
 
+mod config;
+mod icons;
 mod renderer;
+mod widget;
 mod window;
+mod data_device;
+mod keyboard;
+mod protocols;
 
 mod nullable;
 mod globals;
+mod geometry;
 mod prelude;
 
 use prelude::*;
@@ -25,15 +37,41 @@ fn main() {
     let mut queue = display.create_event_queue();
     queue.sync_roundtrip(&mut (), |_, _, _| {}).unwrap();
     
-    let global_manager = GlobalManager::new(&display.attach(queue.token()));
+    let attached_display = display.attach(queue.token());
+    let global_manager = GlobalManager::new(&attached_display);
     queue.sync_roundtrip(&mut (), |_, _, _| {}).unwrap();
-    let globals = GlobalsHandle::<XdgGlobals>::new(global_manager, &display);
+    let mut event_loop = EventLoop::<()>::try_new().expect("Failed to create the event loop");
+    let loop_handle = event_loop.handle();
+    let globals = GlobalsHandle::<XdgGlobals>::new(global_manager, &display, &attached_display, loop_handle);
     unsafe { GLOBALS.put(globals.clone()) };
+    // For the formats wl_shm sends once bound
+    queue.sync_roundtrip(&mut (), |_, _, _| {}).unwrap();
+    if let Err(e) = globals.pixel_format() {
+        eprintln!("{e}");
+        std::process::exit(1);
+    }
+    WaylandSource::new(queue).quick_insert(event_loop.handle()).unwrap();
+    if let Some(fd) = globals.config.fd() {
+        let config = globals.config.clone();
+        let source = Generic::new(fd, Interest::READ, Mode::Level);
+        let inserted = event_loop.handle().insert_source(source, move |_, _, _| {
+            config.dispatch();
+            Ok(PostAction::Continue)
+        });
+        if let Err(e) = inserted {
+            eprintln!("Failed to watch the config: {e}");
+        }
+    }
     let window = globals.new_window();
     loop {
         if window.should_close() {
             break;
         }
-        queue.dispatch(&mut (), |_, _, _| {}).unwrap();
+        // Requests made outside of Wayland events, like redraws of key repeats
+        match display.flush() {
+            Err(e) if e.kind() != io::ErrorKind::WouldBlock => panic!("Failed to flush the display: {e}"),
+            _ => (),
+        }
+        event_loop.dispatch(None, &mut ()).unwrap();
     }
 }
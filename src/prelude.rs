@@ -1,6 +1,7 @@
 use std::cell::{Ref, RefMut};
 use std::{rc::Rc, cell::RefCell};
 
+pub use crate::geometry::{Insets, Rect, Size};
 pub use crate::globals::GlobalsHandle;
 pub use crate::nullable::Nullable;
 
@@ -0,0 +1,36 @@
+// Client bindings generated by build.rs, laid out like the ones in `wayland-protocols`
+macro_rules! generated_protocol {
+    ($name: expr, [$($import: ident),*], [$($prot_import: path),*]) => {
+        pub use self::generated::*;
+
+        mod generated {
+            #![allow(dead_code, non_camel_case_types, unused_unsafe, unused_variables)]
+            #![allow(non_upper_case_globals, non_snake_case, unused_imports, static_mut_refs)]
+            #![allow(missing_docs, clippy::all)]
+
+            pub(crate) use wayland_client::{Main, Attached, Proxy, ProxyMap, AnonymousObject};
+            pub(crate) use wayland_commons::map::{Object, ObjectMetadata};
+            pub(crate) use wayland_commons::{Interface, MessageGroup};
+            pub(crate) use wayland_commons::wire::{Argument, MessageDesc, ArgumentType, Message};
+            pub(crate) use wayland_commons::smallvec;
+            pub(crate) use wayland_client::protocol::{$($import),*};
+            pub(crate) use wayland_client::sys;
+            $(
+                pub(crate) use $prot_import;
+            )*
+            include!(concat!(env!("OUT_DIR"), "/", $name, "_client_api.rs"));
+        }
+    };
+}
+
+pub mod cursor_shape {
+    generated_protocol!(
+        "cursor-shape-v1",
+        [wl_pointer],
+        [wayland_protocols::unstable::tablet::v2::client::zwp_tablet_tool_v2]
+    );
+}
+
+pub mod pointer_gestures {
+    generated_protocol!("pointer-gestures-unstable-v1", [wl_surface, wl_pointer], []);
+}
@@ -0,0 +1,481 @@
+use std::cell::{Cell, RefCell};
+use std::ops::Range;
+
+use cosmic_text::{Attrs, Buffer, Family, FontSystem, Metrics, SwashCache};
+use rgb::alt::BGRA8;
+use rgb::{FromSlice, RGBA8};
+
+use crate::prelude::*;
+use crate::window::Pixels;
+
+pub mod format;
+pub mod image;
+pub mod svg;
+
+pub use self::image::{Filter, Image, ImageCache, ScaleMode};
+pub use svg::Svg;
+pub use format::PixelFormat;
+
+// Memory layout of wl_shm's 32 bit formats on little endian. Colors are premultiplied by
+// alpha, as Argb8888 wants them.
+pub type Pixel = BGRA8;
+
+pub fn premultiply(color: RGBA8) -> Pixel {
+    let alpha = color.a as u32;
+    let channel = |c: u8| ((c as u32 * alpha + 127) / 255) as u8;
+    Pixel {
+        b: channel(color.b),
+        g: channel(color.g),
+        r: channel(color.r),
+        a: color.a,
+    }
+}
+
+// Past this many damaged rectangles, they are merged into one
+const MAX_DAMAGE_RECTS: usize = 8;
+
+/// Parts of a surface that changed since it was last drawn, in surface coordinates.
+/// Overlapping rectangles are merged.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Damage {
+    rects: Vec<Rect>,
+}
+
+impl Damage {
+    pub fn add(&mut self, mut rect: Rect) {
+        if rect.is_empty() {
+            return;
+        }
+        // The merged rectangle may overlap ones that the original didn't
+        while let Some(i) = self.rects.iter().position(|other| other.intersects(rect)) {
+            rect = rect.union(self.rects.swap_remove(i));
+        }
+        self.rects.push(rect);
+        if self.rects.len() > MAX_DAMAGE_RECTS {
+            let bounds = self.rects.drain(..).reduce(|a, b| a.union(b));
+            self.rects.extend(bounds);
+        }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.rects.is_empty()
+    }
+    pub fn rects(&self) -> &[Rect] {
+        &self.rects
+    }
+}
+
+/// What was drawn last, kept to only repaint the damage into the next buffer
+#[derive(Default)]
+pub struct FrameBuffer {
+    buf: Vec<u8>,
+    /// `buf` in the format it is sent in, when that isn't the one it is drawn in
+    converted: Vec<u8>,
+    width: usize,
+    height: usize,
+}
+
+impl std::fmt::Debug for FrameBuffer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct(std::any::type_name::<Self>())
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .finish()
+    }
+}
+
+impl FrameBuffer {
+    /// Returns `true` if the size changed, the contents are lost then
+    pub fn resize(&mut self, width: usize, height: usize) -> bool {
+        if (width, height) == (self.width, self.height) {
+            return false;
+        }
+        self.buf = vec![0; width * height * 4];
+        self.converted.clear();
+        self.width = width;
+        self.height = height;
+        true
+    }
+    pub fn canvas(&mut self) -> Canvas<'_> {
+        Canvas::new(&mut self.buf, self.width, self.height)
+    }
+    /// In the memory layout of the 32 bit wl_shm formats
+    pub fn bytes(&self) -> &[u8] {
+        &self.buf
+    }
+    /// The frame in `format`, converted again only where `damage` says it changed. The
+    /// format is expected to stay the same.
+    pub fn converted(&mut self, format: PixelFormat, damage: &[Rect]) -> &[u8] {
+        if format.is_native() {
+            return &self.buf;
+        }
+        let len = self.width * self.height * format.bytes_per_pixel();
+        if self.converted.len() != len {
+            self.converted = vec![0; len];
+            format.convert(&self.buf, &mut self.converted);
+            return &self.converted;
+        }
+        for rect in damage {
+            format.convert_rect(&self.buf, &mut self.converted, self.width, *rect);
+        }
+        &self.converted
+    }
+}
+
+/// A shm buffer being drawn to
+pub struct Canvas<'a> {
+    pixels: &'a mut [Pixel],
+    width: usize,
+    height: usize,
+    /// Nothing is drawn outside of it
+    clip: Rect,
+}
+
+impl<'a> Canvas<'a> {
+    pub fn new(buf: &'a mut [u8], width: usize, height: usize) -> Self {
+        Self {
+            pixels: buf.as_bgra_mut(),
+            width,
+            height,
+            clip: Rect::new(0, 0, width as i32, height as i32),
+        }
+    }
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    pub fn height(&self) -> usize {
+        self.height
+    }
+    pub fn clip(&self) -> Rect {
+        self.clip
+    }
+    /// Restricts drawing to `clip`, `None` allows the whole canvas again
+    pub fn set_clip(&mut self, clip: Option<Rect>) {
+        let bounds = Rect::new(0, 0, self.width as i32, self.height as i32);
+        self.clip = clip.map_or(bounds, |clip| clip.intersect(bounds));
+    }
+    pub fn fill(&mut self, color: RGBA8) {
+        self.fill_rect(Rect::new(0, 0, self.width as i32, self.height as i32), color)
+    }
+    /// Replaces everything with `color`, translucent colors included
+    pub fn clear(&mut self, color: RGBA8) {
+        self.clear_rect(Rect::new(0, 0, self.width as i32, self.height as i32), color)
+    }
+    /// Replaces the pixels of `rect` with `color` instead of drawing over them
+    pub fn clear_rect(&mut self, rect: Rect, color: RGBA8) {
+        let rect = rect.intersect(self.clip);
+        if rect.is_empty() {
+            return;
+        }
+        let pixel = premultiply(color);
+        for y in rect.y..rect.y + rect.height {
+            let start = y as usize * self.width + rect.x as usize;
+            self.pixels[start..start + rect.width as usize].fill(pixel);
+        }
+    }
+    pub fn fill_rect(&mut self, rect: Rect, color: RGBA8) {
+        let rect = rect.intersect(self.clip);
+        if rect.is_empty() {
+            return;
+        }
+        let (x0, y0) = (rect.x as usize, rect.y as usize);
+        let (x1, y1) = (x0 + rect.width as usize, y0 + rect.height as usize);
+        for y in y0..y1 {
+            for pixel in &mut self.pixels[y * self.width + x0..y * self.width + x1] {
+                blend(pixel, color);
+            }
+        }
+    }
+    /// Outline `width` pixels thick, inside `rect`
+    pub fn stroke_rect(&mut self, rect: Rect, width: i32, color: RGBA8) {
+        let Rect { x, y, width: w, height: h } = rect;
+        self.fill_rect(Rect::new(x, y, w, width), color);
+        self.fill_rect(Rect::new(x, y + h - width, w, width), color);
+        self.fill_rect(Rect::new(x, y + width, width, h - 2 * width), color);
+        self.fill_rect(Rect::new(x + w - width, y + width, width, h - 2 * width), color);
+    }
+    /// Antialiased disk centered on `cx`, `cy`
+    pub fn fill_circle(&mut self, cx: f32, cy: f32, radius: f32, color: RGBA8) {
+        let (x0, x1) = ((cx - radius).floor() as i32, (cx + radius).ceil() as i32);
+        let (y0, y1) = ((cy - radius).floor() as i32, (cy + radius).ceil() as i32);
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let (dx, dy) = (x as f32 + 0.5 - cx, y as f32 + 0.5 - cy);
+                let coverage = (radius + 0.5 - (dx * dx + dy * dy).sqrt()).clamp(0.0, 1.0);
+                if coverage > 0.0 {
+                    let alpha = (color.a as f32 * coverage) as u8;
+                    self.blend_pixel(x, y, RGBA8 { a: alpha, ..color });
+                }
+            }
+        }
+    }
+    /// Copy of the pixels in `rect`, row by row
+    pub fn read_rect(&self, rect: Rect) -> Vec<Pixel> {
+        let rect = rect.intersect(Rect::new(0, 0, self.width as i32, self.height as i32));
+        let mut pixels = Vec::with_capacity((rect.width * rect.height) as usize);
+        for y in rect.y..rect.y + rect.height {
+            let start = y as usize * self.width + rect.x as usize;
+            pixels.extend_from_slice(&self.pixels[start..start + rect.width as usize]);
+        }
+        pixels
+    }
+    /// Mixes `pixels`, read from the same `rect` by [`Canvas::read_rect`], back over it.
+    /// `amount` 1 restores them entirely.
+    pub fn mix_rect(&mut self, rect: Rect, pixels: &[Pixel], amount: f32) {
+        let rect = rect.intersect(Rect::new(0, 0, self.width as i32, self.height as i32));
+        let amount = (amount.clamp(0.0, 1.0) * 255.0).round() as u32;
+        let mix = |s: u8, d: u8| ((s as u32 * amount + d as u32 * (255 - amount) + 127) / 255) as u8;
+        let rows = pixels.chunks_exact(rect.width.max(1) as usize);
+        for (y, row) in (rect.y..rect.y + rect.height).zip(rows) {
+            let start = y as usize * self.width + rect.x as usize;
+            for (dst, src) in self.pixels[start..start + row.len()].iter_mut().zip(row) {
+                *dst = Pixel {
+                    b: mix(src.b, dst.b),
+                    g: mix(src.g, dst.g),
+                    r: mix(src.r, dst.r),
+                    a: mix(src.a, dst.a),
+                };
+            }
+        }
+    }
+    /// Draws `pixels` over the canvas, with their top left corner at `x`, `y`
+    pub fn draw_pixels(&mut self, x: i32, y: i32, pixels: &Pixels) {
+        let width = pixels.width.max(1);
+        let rows = (pixels.buf.len() / width) as i32;
+        let rect = Rect::new(x, y, pixels.width as i32, rows).intersect(self.clip);
+        for row in rect.y..rect.y + rect.height {
+            let src = (row - y) as usize * width + (rect.x - x) as usize;
+            let dst = row as usize * self.width + rect.x as usize;
+            let len = rect.width as usize;
+            for (dst, src) in self.pixels[dst..dst + len].iter_mut().zip(&pixels.buf[src..src + len]) {
+                blend_premultiplied(dst, *src);
+            }
+        }
+    }
+    /// Fades out the corners of `rect` past curves of `radius`, antialiased. The rest is left
+    /// alone.
+    pub fn round_corners(&mut self, rect: Rect, radius: i32) {
+        let radius = clamp_radius(rect, radius);
+        let Rect { x, y, width, height } = rect;
+        let corners = [
+            Rect::new(x, y, radius, radius),
+            Rect::new(x + width - radius, y, radius, radius),
+            Rect::new(x, y + height - radius, radius, radius),
+            Rect::new(x + width - radius, y + height - radius, radius, radius),
+        ];
+        for corner in corners {
+            self.scale_rect(corner, |x, y| rounded_coverage(rect, radius, x, y));
+        }
+    }
+    /// Clears what `rect` with corners rounded by `radius` covers, antialiased, keeping what is
+    /// around it
+    pub fn cut_out_rounded(&mut self, rect: Rect, radius: i32) {
+        let radius = clamp_radius(rect, radius);
+        self.scale_rect(rect, |x, y| 1.0 - rounded_coverage(rect, radius, x, y));
+    }
+    /// Multiplies the pixels of `rect` by what `amount` gives for their coordinates
+    fn scale_rect(&mut self, rect: Rect, amount: impl Fn(i32, i32) -> f32) {
+        let rect = rect.intersect(self.clip);
+        for y in rect.y..rect.y + rect.height {
+            for x in rect.x..rect.x + rect.width {
+                let amount = amount(x, y);
+                if amount >= 1.0 {
+                    continue;
+                }
+                let amount = (amount.max(0.0) * 255.0).round() as u32;
+                let scale = |c: u8| ((c as u32 * amount + 127) / 255) as u8;
+                let pixel = &mut self.pixels[y as usize * self.width + x as usize];
+                *pixel = Pixel {
+                    b: scale(pixel.b),
+                    g: scale(pixel.g),
+                    r: scale(pixel.r),
+                    a: scale(pixel.a),
+                };
+            }
+        }
+    }
+    pub fn blend_pixel(&mut self, x: i32, y: i32, color: RGBA8) {
+        if !self.clip.contains(x as f64, y as f64) {
+            return;
+        }
+        blend(&mut self.pixels[y as usize * self.width + x as usize], color)
+    }
+}
+
+/// Corners can't be rounded past half of the shorter side
+fn clamp_radius(rect: Rect, radius: i32) -> i32 {
+    radius.clamp(0, rect.width.min(rect.height).max(0) / 2)
+}
+
+/// How much of the pixel at `x`, `y` is inside `rect` with corners rounded by `radius`
+fn rounded_coverage(rect: Rect, radius: i32, x: i32, y: i32) -> f32 {
+    if !rect.contains(x as f64, y as f64) {
+        return 0.0;
+    }
+    let radius = radius as f32;
+    let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+    // Distance from the centers of the corner curves, 0 along the straight edges
+    let (left, right) = (rect.x as f32 + radius, (rect.x + rect.width) as f32 - radius);
+    let (top, bottom) = (rect.y as f32 + radius, (rect.y + rect.height) as f32 - radius);
+    let dx = (left - px).max(px - right).max(0.0);
+    let dy = (top - py).max(py - bottom).max(0.0);
+    if dx == 0.0 || dy == 0.0 {
+        return 1.0;
+    }
+    (radius + 0.5 - (dx * dx + dy * dy).sqrt()).clamp(0.0, 1.0)
+}
+
+/// The shape [`Canvas::round_corners`] leaves of `rect`, as rectangles covering the pixels
+/// that are at least `min_coverage` inside it. For input and opaque regions.
+pub fn rounded_rects(rect: Rect, radius: i32, min_coverage: f32) -> Vec<Rect> {
+    let radius = clamp_radius(rect, radius);
+    let inset = |row: i32| {
+        (0..radius)
+            .find(|dx| rounded_coverage(rect, radius, rect.x + dx, rect.y + row) >= min_coverage)
+            .unwrap_or(radius)
+    };
+    let mut rects = Vec::with_capacity(2 * radius as usize + 1);
+    for row in 0..radius {
+        let inset = inset(row);
+        let width = rect.width - 2 * inset;
+        rects.push(Rect::new(rect.x + inset, rect.y + row, width, 1));
+        rects.push(Rect::new(rect.x + inset, rect.y + rect.height - 1 - row, width, 1));
+    }
+    rects.push(Rect::new(rect.x, rect.y + radius, rect.width, rect.height - 2 * radius));
+    rects.retain(|rect| !rect.is_empty());
+    rects
+}
+
+/// Draws `src` over the premultiplied `dst`
+fn blend(dst: &mut Pixel, src: RGBA8) {
+    let alpha = src.a as u32;
+    let mix = |s: u8, d: u8| ((s as u32 * alpha + d as u32 * (255 - alpha) + 127) / 255) as u8;
+    dst.r = mix(src.r, dst.r);
+    dst.g = mix(src.g, dst.g);
+    dst.b = mix(src.b, dst.b);
+    dst.a = mix(0xff, dst.a);
+}
+
+/// Draws `src` over `dst`, both premultiplied
+fn blend_premultiplied(dst: &mut Pixel, src: Pixel) {
+    let remaining = 255 - src.a as u32;
+    let mix = |s: u8, d: u8| s.saturating_add(((d as u32 * remaining + 127) / 255) as u8);
+    dst.r = mix(src.r, dst.r);
+    dst.g = mix(src.g, dst.g);
+    dst.b = mix(src.b, dst.b);
+    dst.a = mix(src.a, dst.a);
+}
+
+thread_local! {
+    // cosmic-text buffers borrow the font system, so it lives for the whole program
+    static FONT_SYSTEM: &'static FontSystem = Box::leak(Box::new(FontSystem::new()));
+    // Attributes borrow the family name as long as the buffer, each name is leaked once
+    static FONT_FAMILIES: RefCell<Vec<&'static str>> = RefCell::default();
+    static FONT_FAMILY: Cell<Option<&'static str>> = Cell::default();
+}
+
+pub fn font_system() -> &'static FontSystem {
+    FONT_SYSTEM.with(|font_system| *font_system)
+}
+
+/// Font of the text laid out from now on, `None` for the default sans-serif
+pub fn set_font_family(family: Option<&str>) {
+    let family = family.map(|family| {
+        FONT_FAMILIES.with(|families| {
+            let mut families = families.borrow_mut();
+            if let Some(known) = families.iter().find(|known| **known == family) {
+                return *known;
+            }
+            let leaked: &'static str = Box::leak(family.into());
+            families.push(leaked);
+            leaked
+        })
+    });
+    FONT_FAMILY.with(|current| current.set(family));
+}
+
+/// Unshaped text, for [`TextRenderer`] or [`text_size`]
+pub fn text_buffer(metrics: Metrics, text: &str) -> Buffer<'static> {
+    let mut buffer = Buffer::new(font_system(), metrics);
+    let attrs = match FONT_FAMILY.with(Cell::get) {
+        Some(family) => Attrs::new().family(Family::Name(family)),
+        None => Attrs::new(),
+    };
+    buffer.set_text(text, attrs);
+    buffer
+}
+
+/// Size of the text laid out without bounds. It changes the size of `buffer`.
+pub fn text_size(buffer: &mut Buffer) -> Size {
+    buffer.set_size(i32::MAX, i32::MAX);
+    buffer.shape_until_scroll();
+    let mut size = Size::default();
+    for run in buffer.layout_runs() {
+        let width = run.glyphs.iter().map(|glyph| glyph.x + glyph.w).fold(0.0, f32::max);
+        size.width = size.width.max(width.ceil() as i32);
+        size.height += buffer.metrics().line_height;
+    }
+    size
+}
+
+/// Draws text laid out by cosmic-text
+pub struct TextRenderer {
+    cache: SwashCache<'static>,
+}
+
+impl TextRenderer {
+    pub fn new() -> Self {
+        Self {
+            cache: SwashCache::new(font_system()),
+        }
+    }
+    pub fn buffer(&self, metrics: Metrics, text: &str) -> Buffer<'static> {
+        text_buffer(metrics, text)
+    }
+    pub fn draw_buffer(&mut self, canvas: &mut Canvas, buffer: &Buffer, origin: (i32, i32), color: RGBA8) {
+        let clip = Rect::new(0, 0, canvas.width() as i32, canvas.height() as i32);
+        self.draw_buffer_clipped(canvas, buffer, origin, clip, color)
+    }
+    /// Like [`TextRenderer::draw_buffer`], leaving everything outside `clip` untouched
+    pub fn draw_buffer_clipped(
+        &mut self,
+        canvas: &mut Canvas,
+        buffer: &Buffer,
+        origin: (i32, i32),
+        clip: Rect,
+        color: RGBA8,
+    ) {
+        let color = cosmic_text::Color::rgba(color.r, color.g, color.b, color.a);
+        buffer.draw(&mut self.cache, color, |x, y, w, h, color| {
+            let color = RGBA8::new(color.r(), color.g(), color.b(), color.a());
+            let rect = Rect::new(origin.0 + x, origin.1 + y, w as i32, h as i32);
+            canvas.fill_rect(rect.intersect(clip), color)
+        });
+    }
+    /// Single line of text, clipped to `rect`
+    pub fn draw_text(&mut self, canvas: &mut Canvas, rect: Rect, text: &str, metrics: Metrics, color: RGBA8) {
+        let mut buffer = self.buffer(metrics, text);
+        buffer.set_size(rect.width, rect.height);
+        buffer.shape_until_scroll();
+        self.draw_buffer(canvas, &buffer, (rect.x, rect.y), color);
+    }
+    /// Underlines the bytes `range` of `line`, the way input method preedit text is shown.
+    pub fn draw_underline(
+        &mut self,
+        canvas: &mut Canvas,
+        buffer: &Buffer,
+        origin: (i32, i32),
+        line: usize,
+        range: Range<usize>,
+        color: RGBA8,
+    ) {
+        for run in buffer.layout_runs().filter(|run| run.line_i == line) {
+            let y = origin.1 + run.line_y + 2;
+            for glyph in run.glyphs.iter() {
+                if glyph.start >= range.start && glyph.end <= range.end {
+                    let x = origin.0 + glyph.x as i32;
+                    canvas.fill_rect(Rect::new(x, y, glyph.w.ceil() as i32, 1), color);
+                }
+            }
+        }
+    }
+}
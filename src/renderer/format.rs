@@ -0,0 +1,120 @@
+use anyhow::bail;
+use rgb::FromSlice;
+use wayland_client::protocol::wl_shm;
+
+use crate::prelude::*;
+
+use super::Pixel;
+
+/// wl_shm formats the renderer can output, in order of preference. It draws in the layout
+/// of `Argb8888`, the others are converted to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PixelFormat {
+    Argb8888,
+    Abgr8888,
+    Xrgb8888,
+    Xbgr8888,
+    Argb2101010,
+    Abgr2101010,
+    Xrgb2101010,
+    Xbgr2101010,
+    Rgb565,
+}
+
+impl PixelFormat {
+    pub const ALL: [Self; 9] = [
+        Self::Argb8888,
+        Self::Abgr8888,
+        Self::Xrgb8888,
+        Self::Xbgr8888,
+        Self::Argb2101010,
+        Self::Abgr2101010,
+        Self::Xrgb2101010,
+        Self::Xbgr2101010,
+        Self::Rgb565,
+    ];
+
+    /// The most preferred of the formats the compositor advertised
+    pub fn pick(supported: &[wl_shm::Format]) -> anyhow::Result<Self> {
+        let picked = Self::ALL.into_iter().find(|format| supported.contains(&format.wl_format()));
+        if let Some(format) = picked {
+            return Ok(format);
+        }
+        bail!(
+            "The compositor supports none of the pixel formats we can draw in. \
+             It advertised {supported:?}, we need one of {:?}",
+            Self::ALL
+        );
+    }
+    pub fn wl_format(self) -> wl_shm::Format {
+        match self {
+            Self::Argb8888 => wl_shm::Format::Argb8888,
+            Self::Abgr8888 => wl_shm::Format::Abgr8888,
+            Self::Xrgb8888 => wl_shm::Format::Xrgb8888,
+            Self::Xbgr8888 => wl_shm::Format::Xbgr8888,
+            Self::Argb2101010 => wl_shm::Format::Argb2101010,
+            Self::Abgr2101010 => wl_shm::Format::Abgr2101010,
+            Self::Xrgb2101010 => wl_shm::Format::Xrgb2101010,
+            Self::Xbgr2101010 => wl_shm::Format::Xbgr2101010,
+            Self::Rgb565 => wl_shm::Format::Rgb565,
+        }
+    }
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            Self::Rgb565 => 2,
+            _ => 4,
+        }
+    }
+    /// Frames are drawn in its layout already, and are sent as they are
+    pub fn is_native(self) -> bool {
+        matches!(self, Self::Argb8888 | Self::Xrgb8888)
+    }
+    /// Without alpha, translucent pixels end up drawn over black
+    pub fn has_alpha(self) -> bool {
+        matches!(self, Self::Argb8888 | Self::Abgr8888 | Self::Argb2101010 | Self::Abgr2101010)
+    }
+    /// Writes `src`, laid out as drawn by [`super::Canvas`], into `dst` in this format.
+    /// Both hold the same number of pixels.
+    pub fn convert(self, src: &[u8], dst: &mut [u8]) {
+        // The channel bits are read as the little endian integers wl_shm formats describe
+        let pack = |pixel: &Pixel| -> u32 {
+            let (r, g, b, a) = (pixel.r as u32, pixel.g as u32, pixel.b as u32, pixel.a as u32);
+            // Repeats the high bits in the low ones, so 0xff becomes 0x3ff
+            let (r10, g10, b10) = ((r << 2) | (r >> 6), (g << 2) | (g >> 6), (b << 2) | (b >> 6));
+            match self {
+                Self::Argb8888 | Self::Xrgb8888 => (a << 24) | (r << 16) | (g << 8) | b,
+                Self::Abgr8888 | Self::Xbgr8888 => (a << 24) | (b << 16) | (g << 8) | r,
+                Self::Argb2101010 => ((a >> 6) << 30) | (r10 << 20) | (g10 << 10) | b10,
+                Self::Abgr2101010 => ((a >> 6) << 30) | (b10 << 20) | (g10 << 10) | r10,
+                Self::Xrgb2101010 => (0b11 << 30) | (r10 << 20) | (g10 << 10) | b10,
+                Self::Xbgr2101010 => (0b11 << 30) | (b10 << 20) | (g10 << 10) | r10,
+                Self::Rgb565 => ((r >> 3) << 11) | ((g >> 2) << 5) | (b >> 3),
+            }
+        };
+        match self {
+            _ if self.is_native() => dst.copy_from_slice(src),
+            Self::Rgb565 => {
+                for (pixel, out) in src.as_bgra().iter().zip(dst.chunks_exact_mut(2)) {
+                    out.copy_from_slice(&(pack(pixel) as u16).to_le_bytes());
+                }
+            }
+            _ => {
+                for (pixel, out) in src.as_bgra().iter().zip(dst.chunks_exact_mut(4)) {
+                    out.copy_from_slice(&pack(pixel).to_le_bytes());
+                }
+            }
+        }
+    }
+    /// Like [`PixelFormat::convert`], only for the pixels of frames `width` wide that are
+    /// in `rect`
+    pub fn convert_rect(self, src: &[u8], dst: &mut [u8], width: usize, rect: Rect) {
+        let height = src.len() / 4 / width.max(1);
+        let rect = rect.intersect(Rect::new(0, 0, width as i32, height as i32));
+        let bytes = self.bytes_per_pixel();
+        for y in rect.y..rect.y + rect.height {
+            let start = y as usize * width + rect.x as usize;
+            let end = start + rect.width as usize;
+            self.convert(&src[start * 4..end * 4], &mut dst[start * bytes..end * bytes]);
+        }
+    }
+}
@@ -0,0 +1,300 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use ::image::imageops::{self, FilterType};
+use ::image::RgbaImage;
+use anyhow::Context;
+use rgb::FromSlice;
+
+use crate::prelude::*;
+use crate::window::Pixels;
+
+use super::svg::{self, Svg};
+use super::{premultiply, Pixel};
+
+const TRANSPARENT: Pixel = Pixel { b: 0, g: 0, r: 0, a: 0 };
+/// Memory the cached renderings may take, past it the least recently used are dropped
+const CACHE_BUDGET: usize = 64 << 20;
+
+/// How images are resampled when scaled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Filter {
+    /// Faster, a bit blurry when shrinking a lot
+    Bilinear,
+    /// Sharpest
+    #[default]
+    Lanczos,
+}
+
+impl From<Filter> for FilterType {
+    fn from(filter: Filter) -> Self {
+        match filter {
+            Filter::Bilinear => FilterType::Triangle,
+            Filter::Lanczos => FilterType::Lanczos3,
+        }
+    }
+}
+
+/// How an image is placed in a size other than its own
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScaleMode {
+    /// Scaled to be entirely visible, keeping its aspect ratio. The sides it doesn't reach
+    /// stay transparent.
+    Fit,
+    /// Scaled to cover everything, keeping its aspect ratio. What sticks out is cropped.
+    Fill,
+    /// Unscaled in the middle, cropped if larger
+    Center,
+    /// Repeated unscaled from the top left corner
+    Tile,
+}
+
+/// A decoded image, premultiplied like what is drawn
+#[derive(Clone, PartialEq, Eq)]
+pub struct Image {
+    pixels: Vec<Pixel>,
+    width: usize,
+    height: usize,
+}
+
+impl std::fmt::Debug for Image {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct(std::any::type_name::<Self>())
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .finish()
+    }
+}
+
+impl Image {
+    /// Decodes a PNG, JPEG or WebP image, told apart by their contents
+    pub fn decode(bytes: &[u8]) -> anyhow::Result<Self> {
+        let image = ::image::load_from_memory(bytes)?.into_rgba8();
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        let pixels = image.into_raw().as_rgba().iter().map(|color| premultiply(*color)).collect();
+        Ok(Self { pixels, width, height })
+    }
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let bytes = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::decode(&bytes).with_context(|| format!("Failed to decode {}", path.display()))
+    }
+    /// `pixels` hold `height` rows of `width`
+    pub(super) fn from_premultiplied(pixels: Vec<Pixel>, width: usize, height: usize) -> Self {
+        debug_assert_eq!(pixels.len(), width * height);
+        Self { pixels, width, height }
+    }
+    pub(super) fn transparent(width: usize, height: usize) -> Self {
+        Self {
+            pixels: vec![TRANSPARENT; width * height],
+            width,
+            height,
+        }
+    }
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    pub fn height(&self) -> usize {
+        self.height
+    }
+    /// Memory taken by its pixels
+    fn bytes(&self) -> usize {
+        self.pixels.len() * std::mem::size_of::<Pixel>()
+    }
+    pub fn pixels(&self) -> Pixels<'_> {
+        Pixels {
+            buf: &self.pixels,
+            width: self.width,
+        }
+    }
+    /// Resampled to `width` by `height`, regardless of its aspect ratio
+    pub fn resize(&self, width: usize, height: usize, filter: Filter) -> Self {
+        if (width, height) == (self.width, self.height) {
+            return self.clone();
+        }
+        if self.pixels.is_empty() {
+            return Self::transparent(width, height);
+        }
+        // Premultiplied, transparent pixels don't bleed their color into the others
+        let raw = self.pixels.iter().flat_map(|pixel| [pixel.r, pixel.g, pixel.b, pixel.a]).collect();
+        let image = RgbaImage::from_raw(self.width as u32, self.height as u32, raw)
+            .expect("Buffer of the image's size");
+        let resized = imageops::resize(&image, width as u32, height as u32, filter.into());
+        let pixels = resized
+            .into_raw()
+            .as_rgba()
+            .iter()
+            // Lanczos overshoots around sharp edges, past what premultiplied colors allow
+            .map(|color| Pixel {
+                b: color.b.min(color.a),
+                g: color.g.min(color.a),
+                r: color.r.min(color.a),
+                a: color.a,
+            })
+            .collect();
+        Self { pixels, width, height }
+    }
+    /// An image of `size`, with this one placed in it as `mode` says
+    pub fn render(&self, size: Size, mode: ScaleMode, filter: Filter) -> Self {
+        let (width, height) = (size.width.max(0) as usize, size.height.max(0) as usize);
+        let mut out = Self::transparent(width, height);
+        if self.pixels.is_empty() || out.pixels.is_empty() {
+            return out;
+        }
+        let image = match mode {
+            ScaleMode::Fit | ScaleMode::Fill => {
+                let scale_x = width as f64 / self.width as f64;
+                let scale_y = height as f64 / self.height as f64;
+                let scale = match mode {
+                    ScaleMode::Fit => scale_x.min(scale_y),
+                    _ => scale_x.max(scale_y),
+                };
+                let scaled = |side: usize| ((side as f64 * scale).round() as usize).max(1);
+                Cow::Owned(self.resize(scaled(self.width), scaled(self.height), filter))
+            }
+            ScaleMode::Center | ScaleMode::Tile => Cow::Borrowed(self),
+        };
+        if mode == ScaleMode::Tile {
+            for (y, row) in out.pixels.chunks_exact_mut(width).enumerate() {
+                let start = (y % image.height) * image.width;
+                let tile = &image.pixels[start..start + image.width];
+                for (out, pixel) in row.iter_mut().zip(tile.iter().cycle()) {
+                    *out = *pixel;
+                }
+            }
+            return out;
+        }
+        // Centered, possibly sticking out on some sides
+        let x = (width as i32 - image.width as i32) / 2;
+        let y = (height as i32 - image.height as i32) / 2;
+        let visible = Rect::new(x, y, image.width as i32, image.height as i32)
+            .intersect(Rect::new(0, 0, width as i32, height as i32));
+        for out_y in visible.y..visible.y + visible.height {
+            let src = (out_y - y) as usize * image.width + (visible.x - x) as usize;
+            let dst = out_y as usize * width + visible.x as usize;
+            let len = visible.width as usize;
+            out.pixels[dst..dst + len].copy_from_slice(&image.pixels[src..src + len]);
+        }
+        out
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    path: PathBuf,
+    size: Size,
+    /// In thousandths, as floats can't be hashed
+    scale: u32,
+    mode: ScaleMode,
+    filter: Filter,
+}
+
+struct CacheEntry {
+    image: Rc<Image>,
+    /// Value of the clock when it was last asked for
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct ImageCacheInner {
+    entries: HashMap<CacheKey, CacheEntry>,
+    /// Ticks on every lookup
+    clock: u64,
+    /// Taken by the images of `entries`
+    bytes: usize,
+}
+
+impl ImageCacheInner {
+    /// Drops the least recently used images until the rest fit in the budget. The last one
+    /// asked for is kept, however large.
+    fn evict(&mut self) {
+        while self.bytes > CACHE_BUDGET && self.entries.len() > 1 {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            let Some(entry) = oldest.and_then(|key| self.entries.remove(&key)) else {
+                break;
+            };
+            self.bytes -= entry.image.bytes();
+        }
+    }
+}
+
+/// Images rendered at the sizes they are shown at, so each is decoded and scaled once. The
+/// least recently used are dropped past a memory budget.
+#[derive(Clone, Default)]
+pub struct ImageCache {
+    inner: RcCell<ImageCacheInner>,
+}
+
+impl std::fmt::Debug for ImageCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let inner = self.inner.borrow();
+        f.debug_struct(std::any::type_name::<Self>())
+            .field("entries", &inner.entries.len())
+            .field("bytes", &inner.bytes)
+            .finish()
+    }
+}
+
+impl ImageCache {
+    /// The image at `path` rendered to `size` times `scale`, loaded the first time it is
+    /// asked for. SVGs are drawn at that size directly instead of being scaled, `filter`
+    /// doesn't apply to them.
+    pub fn get(
+        &self,
+        path: &Path,
+        size: Size,
+        scale: f64,
+        mode: ScaleMode,
+        filter: Filter,
+    ) -> anyhow::Result<Rc<Image>> {
+        let key = CacheKey {
+            path: path.to_owned(),
+            size,
+            scale: (scale * 1000.0).round() as u32,
+            mode,
+            filter,
+        };
+        {
+            let mut inner = self.inner.borrow_mut();
+            inner.clock += 1;
+            let clock = inner.clock;
+            if let Some(entry) = inner.entries.get_mut(&key) {
+                entry.last_used = clock;
+                return Ok(entry.image.clone());
+            }
+        }
+        let image = match svg::is_svg(path) {
+            true => Svg::open(path)?.render(size, scale, mode),
+            false => Image::open(path)?.render(size.scale(scale), mode, filter),
+        };
+        let image = Rc::new(image);
+        let mut inner = self.inner.borrow_mut();
+        inner.bytes += image.bytes();
+        let last_used = inner.clock;
+        inner.entries.insert(
+            key,
+            CacheEntry {
+                image: image.clone(),
+                last_used,
+            },
+        );
+        inner.evict();
+        Ok(image)
+    }
+    /// Forgets the renderings of `path`, so changes to the file are seen
+    pub fn invalidate(&self, path: &Path) {
+        let mut inner = self.inner.borrow_mut();
+        inner.entries.retain(|key, _| key.path != path);
+        inner.bytes = inner.entries.values().map(|entry| entry.image.bytes()).sum();
+    }
+    pub fn clear(&self) {
+        let mut inner = self.inner.borrow_mut();
+        inner.entries.clear();
+        inner.bytes = 0;
+    }
+}
@@ -0,0 +1,83 @@
+use std::path::Path;
+
+use anyhow::Context;
+use resvg::tiny_skia::{Pixmap, Transform};
+use resvg::usvg::{self, FitTo, Tree};
+use rgb::FromSlice;
+
+use crate::prelude::*;
+
+use super::image::{Filter, Image, ScaleMode};
+use super::Pixel;
+
+/// Whether `path` is named like an SVG document, compressed or not
+pub fn is_svg(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "svg" || ext == "svgz")
+}
+
+/// A parsed SVG document, rasterized at whatever size it is shown at
+pub struct Svg {
+    tree: Tree,
+}
+
+impl std::fmt::Debug for Svg {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct(std::any::type_name::<Self>())
+            .field("size", &self.size())
+            .finish()
+    }
+}
+
+impl Svg {
+    /// Parses an SVG document, gzipped ones too
+    pub fn parse(data: &[u8]) -> anyhow::Result<Self> {
+        let tree = Tree::from_data(data, &usvg::Options::default())?;
+        Ok(Self { tree })
+    }
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let data = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::parse(&data).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+    /// Size the document was made at
+    pub fn size(&self) -> (f64, f64) {
+        (self.tree.size.width(), self.tree.size.height())
+    }
+    /// An image of `size` times `scale`, with the document placed in it as `mode` says. It is
+    /// drawn at the final size, so it stays sharp at fractional scales.
+    pub fn render(&self, size: Size, scale: f64, mode: ScaleMode) -> Image {
+        let (width, height) = self.size();
+        let target = size.scale(scale);
+        let zoom = match mode {
+            ScaleMode::Fit => (target.width as f64 / width).min(target.height as f64 / height),
+            ScaleMode::Fill => (target.width as f64 / width).max(target.height as f64 / height),
+            ScaleMode::Center | ScaleMode::Tile => scale,
+        };
+        let zoomed = |side: f64| (side * zoom).round().max(1.0) as u32;
+        let drawn = self.rasterize(zoomed(width), zoomed(height));
+        // Already at its final size, only placed
+        let placement = match mode {
+            ScaleMode::Tile => ScaleMode::Tile,
+            _ => ScaleMode::Center,
+        };
+        drawn.render(target, placement, Filter::default())
+    }
+    fn rasterize(&self, width: u32, height: u32) -> Image {
+        let Some(mut pixmap) = Pixmap::new(width, height) else {
+            return Image::transparent(width as usize, height as usize);
+        };
+        resvg::render(&self.tree, FitTo::Size(width, height), Transform::default(), pixmap.as_mut());
+        // tiny-skia is premultiplied too, only the order of the channels differs
+        let pixels = pixmap
+            .data()
+            .as_rgba()
+            .iter()
+            .map(|color| Pixel {
+                b: color.b,
+                g: color.g,
+                r: color.r,
+                a: color.a,
+            })
+            .collect();
+        Image::from_premultiplied(pixels, width as usize, height as usize)
+    }
+}
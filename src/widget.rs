@@ -0,0 +1,885 @@
+use std::any::Any;
+use std::cell::RefCell;
+use std::ops::Range;
+use std::rc::Rc;
+use std::time::Instant;
+
+use rgb::RGBA8;
+use wayland_client::protocol::wl_surface::WlSurface;
+
+use crate::data_device::{pick_mime_type, DataDevice, DndAction, DragOffer, DragSource, DropEvent};
+use crate::keyboard::KeyEvent;
+use crate::prelude::*;
+use crate::window::xdg_shell::TextInputClient;
+use crate::renderer::{text_buffer, text_size, Canvas, Damage, TextRenderer};
+
+pub mod animation;
+pub mod button;
+pub mod checkbox;
+pub mod focus;
+pub mod label;
+pub mod layout;
+pub mod progress_bar;
+pub mod slider;
+pub mod text_entry;
+pub mod theme;
+pub mod toggle;
+
+pub use animation::{Animation, Easing, Spring, Transition};
+pub use button::Button;
+pub use checkbox::Checkbox;
+pub use focus::{Accelerator, MnemonicText};
+pub use label::Label;
+pub use layout::{Align, Direction, Justify, Layout};
+pub use progress_bar::ProgressBar;
+pub use slider::Slider;
+pub use text_entry::TextEntry;
+pub use theme::{Shadow, Theme, ThemeConfig, ThemeVariant};
+pub use toggle::Toggle;
+
+// Linux input event codes of the pointer buttons
+pub const BTN_LEFT: u32 = 0x110;
+pub const BTN_RIGHT: u32 = 0x111;
+pub const BTN_MIDDLE: u32 = 0x112;
+
+/// Handle to a widget in a [`WidgetTree`]. Stale once the widget is removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WidgetId(usize);
+
+/// Pointer input in surface coordinates, as sent by the window
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PointerEvent {
+    Motion { x: f64, y: f64 },
+    /// `button` is a linux input event code, like `BTN_LEFT`
+    Button { button: u32, pressed: bool, serial: u32 },
+    Leave,
+}
+
+/// Input sent to a single widget
+#[derive(Debug, Clone)]
+pub enum WidgetEvent {
+    PointerEnter,
+    PointerLeave,
+    /// Sent to the widget under the pointer, or to the pressed one while a button is held
+    PointerMotion { x: f64, y: f64 },
+    /// Presses go to the widget under the pointer, releases to the one that was pressed
+    /// `serial` is needed to set the clipboard or start a drag from it
+    PointerButton { button: u32, pressed: bool, x: f64, y: f64, serial: u32 },
+    /// Sent to the focused widget
+    Key(KeyEvent),
+    /// The input method changed the focused widget through [`Widget::text_input_client`]
+    InputMethod,
+    /// The widget got the keyboard focus, or the window got it back
+    FocusIn,
+    FocusOut,
+    /// Its mnemonic or accelerator was pressed
+    Activate,
+    /// A drag the widget takes, as its [`Widget::drop_filter`] says, went over it
+    DragEnter { x: f64, y: f64 },
+    DragMotion { x: f64, y: f64 },
+    DragLeave,
+    /// The data can now be read from `offer` as `mime_type`. The offer must be finished or
+    /// cancelled afterwards, its `action` says what to do with the data.
+    Drop { offer: DragOffer, mime_type: String, x: f64, y: f64 },
+    /// A new frame is being drawn, sent to the widgets that asked for it with
+    /// [`EventCtx::request_animation_frame`]. Disabled ones get it too.
+    AnimationFrame(Instant),
+}
+
+/// How the user is interacting with a widget, drawn by the widget
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WidgetState {
+    pub hovered: bool,
+    pub pressed: bool,
+    pub focused: bool,
+    pub disabled: bool,
+}
+
+/// What a widget takes drops of, see [`Widget::drop_filter`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DropFilter {
+    /// In order of preference
+    pub mime_types: Vec<String>,
+    pub actions: DndAction,
+    /// One of `actions`, used unless the user picks another
+    pub preferred: DndAction,
+}
+
+impl DropFilter {
+    /// Takes `mime_types` to copy them
+    pub fn new(mime_types: &[&str]) -> Self {
+        Self {
+            mime_types: mime_types.iter().map(|mime_type| mime_type.to_string()).collect(),
+            actions: DndAction::Copy,
+            preferred: DndAction::Copy,
+        }
+    }
+    pub fn actions(mut self, actions: DndAction, preferred: DndAction) -> Self {
+        self.actions = actions;
+        self.preferred = preferred;
+        self
+    }
+    /// The type to read out of `offered`, `None` if none of them is taken
+    pub fn pick(&self, offered: &[String]) -> Option<String> {
+        self.mime_types.iter().find_map(|wanted| pick_mime_type(offered, wanted))
+    }
+}
+
+pub trait ClickCb = Fn();
+pub trait ToggleCb = Fn(bool);
+pub trait ValueCb = Fn(f64);
+pub trait TextCb = Fn(&str);
+
+/// Code to run once the tree isn't borrowed anymore, like the callbacks of widgets
+pub type Deferred = Box<dyn FnOnce()>;
+
+pub struct DrawCtx<'a, 'b> {
+    pub canvas: &'a mut Canvas<'b>,
+    pub text: &'a mut TextRenderer,
+    pub theme: &'a Theme,
+    pub rect: Rect,
+    pub state: WidgetState,
+    /// Alt is held, mnemonics get underlined
+    pub show_mnemonics: bool,
+}
+
+impl DrawCtx<'_, '_> {
+    /// Single line of `text`, vertically centered in `rect`
+    pub fn text(&mut self, rect: Rect, text: &str, color: RGBA8) {
+        self.text_at(rect, text, None, color, false)
+    }
+    /// Single line of `text`, centered in `rect`
+    pub fn centered_text(&mut self, rect: Rect, text: &str, color: RGBA8) {
+        self.text_at(rect, text, None, color, true)
+    }
+    /// Like [`DrawCtx::text`], underlining the mnemonic while Alt is held
+    pub fn label(&mut self, rect: Rect, label: &MnemonicText, color: RGBA8) {
+        let underline = label.range().filter(|_| self.show_mnemonics);
+        self.text_at(rect, label.text(), underline, color, false)
+    }
+    /// Like [`DrawCtx::centered_text`], underlining the mnemonic while Alt is held
+    pub fn centered_label(&mut self, rect: Rect, label: &MnemonicText, color: RGBA8) {
+        let underline = label.range().filter(|_| self.show_mnemonics);
+        self.text_at(rect, label.text(), underline, color, true)
+    }
+    fn text_at(&mut self, rect: Rect, text: &str, underline: Option<Range<usize>>, color: RGBA8, center: bool) {
+        let mut buffer = text_buffer(self.theme.metrics(), text);
+        let size = text_size(&mut buffer);
+        let x = if center { rect.x + (rect.width - size.width) / 2 } else { rect.x };
+        let y = rect.y + (rect.height - size.height) / 2;
+        let rect = Rect::new(x, y, size.width.min(rect.width) + 1, size.height);
+        buffer.set_size(rect.width, rect.height);
+        buffer.shape_until_scroll();
+        self.text.draw_buffer(self.canvas, &buffer, (rect.x, rect.y), color);
+        if let Some(underline) = underline {
+            self.text.draw_underline(self.canvas, &buffer, (rect.x, rect.y), 0, underline, color);
+        }
+    }
+}
+
+pub struct EventCtx<'a> {
+    pub theme: &'a Theme,
+    pub rect: Rect,
+    pub state: WidgetState,
+    redraw: bool,
+    layout: bool,
+    animate: bool,
+    deferred: &'a mut Vec<Deferred>,
+    drag_origin: Option<&'a (DataDevice, WlSurface)>,
+}
+
+impl<'a> EventCtx<'a> {
+    pub fn request_redraw(&mut self) {
+        self.redraw = true;
+    }
+    /// The widget's size changed
+    pub fn request_layout(&mut self) {
+        self.layout = true;
+    }
+    /// Sends [`WidgetEvent::AnimationFrame`] on the next frame. Animations ask again on
+    /// each frame until they are done.
+    pub fn request_animation_frame(&mut self) {
+        self.animate = true;
+    }
+    /// Runs `f` after the event went through, when the application can use the tree again
+    pub fn defer(&mut self, f: impl FnOnce() + 'static) {
+        self.deferred.push(Box::new(f));
+    }
+    /// Starts building a drag out of the widget's window, to start with the `serial` of a
+    /// [`WidgetEvent::PointerButton`] press. `None` if the compositor has no data device.
+    pub fn drag(&self, mime_types: &[&str], actions: DndAction) -> Option<DragSource> {
+        let (device, surface) = self.drag_origin?;
+        Some(device.drag(surface, mime_types, actions))
+    }
+}
+
+pub trait Widget: Any {
+    /// Size of the content, without padding. Children are measured separately.
+    fn measure(&mut self, _theme: &Theme) -> Size {
+        Size::default()
+    }
+    /// Drawn before the children, which end up on top
+    fn draw(&mut self, _ctx: &mut DrawCtx) {}
+    /// Whether pressing it gives it the keyboard focus
+    fn focusable(&self) -> bool {
+        false
+    }
+    fn event(&mut self, _evt: WidgetEvent, _ctx: &mut EventCtx) {}
+    /// Lower case character that, with Alt, activates the widget
+    fn mnemonic(&self) -> Option<char> {
+        None
+    }
+    /// Gets the input method text while the widget has focus
+    fn text_input_client(&self) -> Option<Rc<RefCell<dyn TextInputClient>>> {
+        None
+    }
+    /// What drops the widget takes, `None` leaves them to its parent
+    fn drop_filter(&self) -> Option<DropFilter> {
+        None
+    }
+}
+
+/// Widget only there to hold and lay out its children
+#[derive(Debug, Clone, Default)]
+pub struct Container {
+    pub background: Option<RGBA8>,
+}
+
+impl Container {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn background(mut self, background: RGBA8) -> Self {
+        self.background = Some(background);
+        self
+    }
+}
+
+impl Widget for Container {
+    fn draw(&mut self, ctx: &mut DrawCtx) {
+        if let Some(background) = self.background {
+            ctx.canvas.fill_rect(ctx.rect, background);
+        }
+    }
+}
+
+struct Node {
+    widget: Box<dyn Widget>,
+    layout: Layout,
+    parent: Option<WidgetId>,
+    children: Vec<WidgetId>,
+    disabled: bool,
+    accelerator: Option<Accelerator>,
+    /// Of the widget and its children
+    opacity: Animation<f32>,
+    offset: Animation<(f32, f32)>,
+    /// Part of the offset already added to `rect`, in whole pixels
+    applied_offset: (i32, i32),
+    /// Computed by the layout pass
+    preferred: Size,
+    rect: Rect,
+}
+
+/// A drag over the window
+struct WidgetDrag {
+    offer: DragOffer,
+    /// Widget it would be dropped on, and the type it reads
+    target: Option<(WidgetId, String)>,
+    pos: (f64, f64),
+}
+
+/// The widgets of a window. They are laid out to fill it, and redrawn where they changed.
+pub struct WidgetTree {
+    nodes: Vec<Option<Node>>,
+    root: WidgetId,
+    size: Size,
+    theme: Theme,
+    text: TextRenderer,
+    hovered: Option<WidgetId>,
+    pressed: Option<WidgetId>,
+    /// Kept while the window doesn't have the keyboard focus, to restore it
+    focused: Option<WidgetId>,
+    keyboard_focus: bool,
+    /// The focus moved through the keyboard, so the focus ring is drawn
+    focus_visible: bool,
+    show_mnemonics: bool,
+    pointer_pos: (f64, f64),
+    drag: Option<WidgetDrag>,
+    /// Data device and surface drags of the widgets start from
+    drag_origin: Option<(DataDevice, WlSurface)>,
+    /// Asked for an animation frame
+    animating: Vec<WidgetId>,
+    needs_layout: bool,
+    /// Areas to draw again
+    damage: Damage,
+}
+
+impl std::fmt::Debug for WidgetTree {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct(std::any::type_name::<Self>())
+            .field("nodes", &self.nodes.iter().flatten().count())
+            .field("size", &self.size)
+            .field("theme", &self.theme)
+            .field("hovered", &self.hovered)
+            .field("pressed", &self.pressed)
+            .field("focused", &self.focused)
+            .field("keyboard_focus", &self.keyboard_focus)
+            .field("needs_layout", &self.needs_layout)
+            .field("damage", &self.damage)
+            .finish()
+    }
+}
+
+impl Default for WidgetTree {
+    fn default() -> Self {
+        Self::new(Container::new(), Layout::column())
+    }
+}
+
+impl WidgetTree {
+    pub fn new(root: impl Widget, layout: Layout) -> Self {
+        Self {
+            nodes: vec![Some(Node {
+                widget: Box::new(root),
+                layout,
+                parent: None,
+                children: Vec::new(),
+                disabled: false,
+                accelerator: None,
+                opacity: Animation::new(1.0, Transition::default()),
+                offset: Animation::new((0.0, 0.0), Transition::default()),
+                applied_offset: (0, 0),
+                preferred: Size::default(),
+                rect: Rect::default(),
+            })],
+            root: WidgetId(0),
+            size: Size::default(),
+            theme: Theme::default(),
+            text: TextRenderer::new(),
+            hovered: None,
+            pressed: None,
+            focused: None,
+            keyboard_focus: false,
+            focus_visible: false,
+            show_mnemonics: false,
+            pointer_pos: (0.0, 0.0),
+            drag: None,
+            drag_origin: None,
+            animating: Vec::new(),
+            needs_layout: true,
+            damage: Damage::default(),
+        }
+    }
+    fn node(&self, id: WidgetId) -> &Node {
+        self.nodes[id.0].as_ref().expect("Widget was removed")
+    }
+    fn node_mut(&mut self, id: WidgetId) -> &mut Node {
+        self.nodes[id.0].as_mut().expect("Widget was removed")
+    }
+    pub fn root(&self) -> WidgetId {
+        self.root
+    }
+    pub fn contains(&self, id: WidgetId) -> bool {
+        matches!(self.nodes.get(id.0), Some(Some(_)))
+    }
+    /// Adds `widget` as the last child of `parent`
+    pub fn add(&mut self, parent: WidgetId, widget: impl Widget, layout: Layout) -> WidgetId {
+        let node = Node {
+            widget: Box::new(widget),
+            layout,
+            parent: Some(parent),
+            children: Vec::new(),
+            disabled: false,
+            accelerator: None,
+            opacity: Animation::new(1.0, Transition::default()),
+            offset: Animation::new((0.0, 0.0), Transition::default()),
+            applied_offset: (0, 0),
+            preferred: Size::default(),
+            rect: Rect::default(),
+        };
+        let id = match self.nodes.iter().position(Option::is_none) {
+            Some(free) => {
+                self.nodes[free] = Some(node);
+                WidgetId(free)
+            }
+            None => {
+                self.nodes.push(Some(node));
+                WidgetId(self.nodes.len() - 1)
+            }
+        };
+        self.node_mut(parent).children.push(id);
+        self.needs_layout = true;
+        id
+    }
+    /// Removes `id` and its children. The root can't be removed.
+    pub fn remove(&mut self, id: WidgetId) {
+        let Some(parent) = self.node(id).parent else {
+            return;
+        };
+        self.damage_widget(id);
+        self.node_mut(parent).children.retain(|child| *child != id);
+        let mut removed = vec![id];
+        while let Some(id) = removed.pop() {
+            if let Some(node) = self.nodes[id.0].take() {
+                removed.extend(node.children);
+            }
+            for tracked in [&mut self.hovered, &mut self.pressed, &mut self.focused] {
+                if *tracked == Some(id) {
+                    *tracked = None;
+                }
+            }
+            self.animating.retain(|animating| *animating != id);
+            if let Some(drag) = &mut self.drag {
+                drag.target = drag.target.take().filter(|(target, _)| *target != id);
+            }
+        }
+        self.needs_layout = true;
+    }
+    pub fn parent(&self, id: WidgetId) -> Option<WidgetId> {
+        self.node(id).parent
+    }
+    pub fn children(&self, id: WidgetId) -> &[WidgetId] {
+        &self.node(id).children
+    }
+    /// `None` if `id` isn't a `W`
+    pub fn widget<W: Widget>(&self, id: WidgetId) -> Option<&W> {
+        (self.node(id).widget.as_ref() as &dyn Any).downcast_ref()
+    }
+    /// Changes may affect the size of the widget, so it is laid out again
+    pub fn widget_mut<W: Widget>(&mut self, id: WidgetId) -> Option<&mut W> {
+        self.needs_layout = true;
+        self.damage_widget(id);
+        (self.node_mut(id).widget.as_mut() as &mut dyn Any).downcast_mut()
+    }
+    pub fn layout(&self, id: WidgetId) -> Layout {
+        self.node(id).layout
+    }
+    pub fn set_layout(&mut self, id: WidgetId, layout: Layout) {
+        self.node_mut(id).layout = layout;
+        self.needs_layout = true;
+    }
+    /// Where the widget was put by the last layout pass
+    pub fn rect(&self, id: WidgetId) -> Rect {
+        self.node(id).rect
+    }
+    /// Disables `id` and its children
+    pub fn set_disabled(&mut self, id: WidgetId, disabled: bool) {
+        self.node_mut(id).disabled = disabled;
+        self.damage_widget(id);
+    }
+    pub fn is_disabled(&self, id: WidgetId) -> bool {
+        let mut ancestor = Some(id);
+        while let Some(id) = ancestor {
+            if self.node(id).disabled {
+                return true;
+            }
+            ancestor = self.node(id).parent;
+        }
+        false
+    }
+    pub fn state(&self, id: WidgetId) -> WidgetState {
+        WidgetState {
+            hovered: self.hovered == Some(id),
+            pressed: self.pressed == Some(id),
+            focused: self.keyboard_focus && self.focused == Some(id),
+            disabled: self.is_disabled(id),
+        }
+    }
+    pub fn theme(&self) -> &Theme {
+        &self.theme
+    }
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+        self.needs_layout = true;
+        self.request_redraw();
+    }
+    pub fn focused(&self) -> Option<WidgetId> {
+        self.focused
+    }
+    /// Lets widgets start drags from `surface`, see [`EventCtx::drag`]
+    pub fn set_drag_origin(&mut self, device: DataDevice, surface: WlSurface) {
+        self.drag_origin = Some((device, surface));
+    }
+    pub fn size(&self) -> Size {
+        self.size
+    }
+    pub fn resize(&mut self, width: i32, height: i32) {
+        let size = Size::new(width, height);
+        if size != self.size {
+            self.size = size;
+            self.needs_layout = true;
+            self.request_redraw();
+        }
+    }
+    pub fn request_layout(&mut self) {
+        self.needs_layout = true;
+    }
+    /// Draws everything again
+    pub fn request_redraw(&mut self) {
+        self.damage.add(Rect::new(0, 0, self.size.width, self.size.height));
+    }
+    /// Draws the area of `id` again
+    pub fn damage_widget(&mut self, id: WidgetId) {
+        let rect = self.node(id).rect;
+        self.damage.add(rect);
+    }
+    pub fn needs_redraw(&self) -> bool {
+        !self.damage.is_empty() || self.needs_layout
+    }
+    /// Lays the widgets out to fill the window, if anything changed since last time
+    pub fn update_layout(&mut self) {
+        if !self.needs_layout {
+            return;
+        }
+        self.needs_layout = false;
+        let before: Vec<Option<Rect>> = self.nodes.iter().map(|node| Some(node.as_ref()?.rect)).collect();
+        layout::measure(self, self.root);
+        let rect = Rect::new(0, 0, self.size.width, self.size.height);
+        layout::arrange(self, self.root, rect);
+        // Parents first, their offset moves the children
+        let mut stack = vec![self.root];
+        while let Some(id) = stack.pop() {
+            self.node_mut(id).applied_offset = (0, 0);
+            self.apply_offset(id);
+            stack.extend(self.node(id).children.iter().copied());
+        }
+        // Where widgets moved from and to
+        for (i, node) in self.nodes.iter().enumerate() {
+            let Some(node) = node else {
+                continue;
+            };
+            let old = before.get(i).copied().flatten().unwrap_or_default();
+            if node.rect != old {
+                self.damage.add(old);
+                self.damage.add(node.rect);
+            }
+        }
+        // Widgets may have moved under the pointer
+        self.hovered = self.hit_test(self.pointer_pos.0, self.pointer_pos.1);
+    }
+    /// Draws the damaged areas again, and returns them. Everything else is left as is, so
+    /// `canvas` should hold what was drawn last time.
+    pub fn draw(&mut self, canvas: &mut Canvas) -> Damage {
+        self.update_layout();
+        let damage = std::mem::take(&mut self.damage);
+        for rect in damage.rects() {
+            canvas.set_clip(Some(*rect));
+            canvas.clear_rect(*rect, self.theme.background);
+            self.draw_clipped(self.root, canvas);
+            self.draw_focus_ring(canvas);
+        }
+        canvas.set_clip(None);
+        damage
+    }
+    /// Where [`WidgetTree::draw`] leaves no transparency, for the compositor to skip what
+    /// is below. Widgets only ever cover the background.
+    pub fn opaque_region(&self) -> Vec<Rect> {
+        if self.theme.background.a != 0xff {
+            return Vec::new();
+        }
+        vec![Rect::new(0, 0, self.size.width, self.size.height)]
+    }
+    /// Draws `id` and its children where they overlap the clip of `canvas`
+    fn draw_clipped(&mut self, id: WidgetId, canvas: &mut Canvas) {
+        let clip = canvas.clip();
+        let state = self.state(id);
+        let node = self.nodes[id.0].as_mut().expect("Widget was removed");
+        let opacity = node.opacity.value().clamp(0.0, 1.0);
+        if opacity == 0.0 {
+            return;
+        }
+        // Translucent widgets are drawn over what was below, then mixed with it
+        let below = (opacity < 1.0).then(|| canvas.read_rect(clip));
+        if node.rect.intersects(clip) {
+            let mut ctx = DrawCtx {
+                canvas,
+                text: &mut self.text,
+                theme: &self.theme,
+                rect: node.rect,
+                state,
+                show_mnemonics: self.show_mnemonics,
+            };
+            node.widget.draw(&mut ctx);
+        }
+        // Children may have been moved out of their parent
+        for child in node.children.clone() {
+            self.draw_clipped(child, canvas);
+        }
+        if let Some(below) = below {
+            canvas.mix_rect(clip, &below, 1.0 - opacity);
+        }
+    }
+    fn draw_focus_ring(&self, canvas: &mut Canvas) {
+        if let Some(focused) = self.focused.filter(|_| self.keyboard_focus && self.focus_visible) {
+            canvas.stroke_rect(self.node(focused).rect, 2, self.theme.focus_ring);
+        }
+    }
+    /// The deepest widget under `x`, `y`, the topmost one where children overlap
+    pub fn hit_test(&self, x: f64, y: f64) -> Option<WidgetId> {
+        if !self.node(self.root).rect.contains(x, y) {
+            return None;
+        }
+        let mut hit = self.root;
+        'descend: loop {
+            for child in self.node(hit).children.iter().rev() {
+                if self.node(*child).rect.contains(x, y) {
+                    hit = *child;
+                    continue 'descend;
+                }
+            }
+            return Some(hit);
+        }
+    }
+    pub fn hovered(&self) -> Option<WidgetId> {
+        self.hovered
+    }
+    /// Sends `evt` to `id`. Disabled widgets get nothing.
+    fn send(&mut self, id: WidgetId, evt: WidgetEvent, deferred: &mut Vec<Deferred>) {
+        let state = self.state(id);
+        if state.disabled && !matches!(evt, WidgetEvent::AnimationFrame(_)) {
+            return;
+        }
+        let node = self.nodes[id.0].as_mut().expect("Widget was removed");
+        let mut ctx = EventCtx {
+            theme: &self.theme,
+            rect: node.rect,
+            state,
+            redraw: false,
+            layout: false,
+            animate: false,
+            deferred,
+            drag_origin: self.drag_origin.as_ref(),
+        };
+        node.widget.event(evt, &mut ctx);
+        let (redraw, layout, animate) = (ctx.redraw, ctx.layout, ctx.animate);
+        if animate && !self.animating.contains(&id) {
+            self.animating.push(id);
+        }
+        if redraw {
+            self.damage_widget(id);
+        }
+        self.needs_layout |= layout;
+    }
+    fn set_hovered(&mut self, hovered: Option<WidgetId>, deferred: &mut Vec<Deferred>) {
+        let old = std::mem::replace(&mut self.hovered, hovered);
+        if old == hovered {
+            return;
+        }
+        if let Some(old) = old {
+            self.damage_widget(old);
+            self.send(old, WidgetEvent::PointerLeave, deferred);
+        }
+        if let Some(hovered) = hovered {
+            self.damage_widget(hovered);
+            self.send(hovered, WidgetEvent::PointerEnter, deferred);
+        }
+    }
+    /// The closest widget to `id` that takes the focus, itself included
+    fn focus_target(&self, id: WidgetId) -> Option<WidgetId> {
+        let mut ancestor = Some(id);
+        while let Some(id) = ancestor {
+            if self.node(id).widget.focusable() {
+                return Some(id);
+            }
+            ancestor = self.node(id).parent;
+        }
+        None
+    }
+    /// Routes pointer input to the widgets. Returns what they deferred, to run once the
+    /// tree isn't borrowed anymore.
+    #[must_use]
+    pub fn pointer_event(&mut self, evt: PointerEvent) -> Vec<Deferred> {
+        let mut deferred = Vec::new();
+        match evt {
+            PointerEvent::Motion { x, y } => {
+                self.pointer_pos = (x, y);
+                let hovered = self.hit_test(x, y);
+                self.set_hovered(hovered, &mut deferred);
+                if let Some(target) = self.pressed.or(hovered) {
+                    self.send(target, WidgetEvent::PointerMotion { x, y }, &mut deferred);
+                }
+            }
+            PointerEvent::Leave => {
+                self.set_hovered(None, &mut deferred);
+                // Starting a drag takes the pointer away, the release never comes
+                if let Some(pressed) = self.pressed.take() {
+                    self.damage_widget(pressed);
+                }
+            }
+            PointerEvent::Button { button, pressed, serial } => {
+                let (x, y) = self.pointer_pos;
+                let evt = WidgetEvent::PointerButton { button, pressed, x, y, serial };
+                if pressed {
+                    let Some(hovered) = self.hovered.filter(|hovered| !self.is_disabled(*hovered)) else {
+                        return deferred;
+                    };
+                    self.pressed = Some(hovered);
+                    self.set_focus_visible(false);
+                    if let Some(focus) = self.focus_target(hovered) {
+                        self.focus(Some(focus), &mut deferred);
+                    }
+                    self.damage_widget(hovered);
+                    self.send(hovered, evt, &mut deferred);
+                } else if let Some(pressed) = self.pressed.take() {
+                    self.damage_widget(pressed);
+                    self.send(pressed, evt, &mut deferred);
+                }
+            }
+        }
+        deferred
+    }
+    /// The closest widget to `x`, `y` taking the drop of `offer`, and the type it reads
+    fn drop_target(&self, offer: &DragOffer, x: f64, y: f64) -> Option<(WidgetId, DropFilter, String)> {
+        let offered = offer.mime_types();
+        let source_actions = offer.source_actions();
+        let mut ancestor = self.hit_test(x, y);
+        while let Some(id) = ancestor {
+            ancestor = self.node(id).parent;
+            let Some(filter) = self.node(id).widget.drop_filter() else {
+                continue;
+            };
+            // Sources older than version 3 don't say, and only copy
+            let allowed = source_actions.is_empty() || source_actions.intersects(filter.actions);
+            if !allowed || self.is_disabled(id) {
+                continue;
+            }
+            if let Some(mime_type) = filter.pick(&offered) {
+                return Some((id, filter, mime_type));
+            }
+        }
+        None
+    }
+    /// Moves the drag to `x`, `y`, telling the source whether it can be dropped there
+    fn move_drag(&mut self, x: f64, y: f64, deferred: &mut Vec<Deferred>) {
+        let Some(drag) = &mut self.drag else {
+            return;
+        };
+        drag.pos = (x, y);
+        let offer = drag.offer.clone();
+        let old = drag.target.as_ref().map(|(id, _)| *id);
+        let target = self.drop_target(&offer, x, y);
+        if target.as_ref().map(|(id, ..)| *id) == old {
+            return;
+        }
+        if let Some(old) = old {
+            self.send(old, WidgetEvent::DragLeave, deferred);
+        }
+        match &target {
+            Some((_, filter, mime_type)) => {
+                offer.accept(Some(mime_type));
+                offer.set_actions(filter.actions, filter.preferred);
+            }
+            None => {
+                offer.accept(None);
+                offer.set_actions(DndAction::None, DndAction::None);
+            }
+        }
+        let target = target.map(|(id, _, mime_type)| (id, mime_type));
+        if let Some(drag) = &mut self.drag {
+            drag.target = target.clone();
+        }
+        if let Some((id, _)) = target {
+            self.send(id, WidgetEvent::DragEnter { x, y }, deferred);
+        }
+    }
+    /// Routes a drag over the window to the widget under it that takes it. Returns what the
+    /// widgets deferred, like [`WidgetTree::pointer_event`].
+    #[must_use]
+    pub fn drop_event(&mut self, evt: DropEvent) -> Vec<Deferred> {
+        let mut deferred = Vec::new();
+        match evt {
+            DropEvent::Enter { offer, x, y } => {
+                self.drag = Some(WidgetDrag { offer, target: None, pos: (x, y) });
+                self.move_drag(x, y, &mut deferred);
+            }
+            DropEvent::Motion { x, y, .. } => {
+                self.move_drag(x, y, &mut deferred);
+                let target = self.drag.as_ref().and_then(|drag| drag.target.as_ref());
+                if let Some((id, _)) = target {
+                    self.send(*id, WidgetEvent::DragMotion { x, y }, &mut deferred);
+                }
+            }
+            DropEvent::Leave => {
+                if let Some((id, _)) = self.drag.take().and_then(|drag| drag.target) {
+                    self.send(id, WidgetEvent::DragLeave, &mut deferred);
+                }
+            }
+            DropEvent::Drop { offer } => {
+                let Some(drag) = self.drag.take() else {
+                    offer.cancel();
+                    return deferred;
+                };
+                let (x, y) = drag.pos;
+                match drag.target.filter(|(id, _)| !self.is_disabled(*id)) {
+                    Some((id, mime_type)) => {
+                        self.send(id, WidgetEvent::Drop { offer, mime_type, x, y }, &mut deferred)
+                    }
+                    None => offer.cancel(),
+                }
+            }
+        }
+        deferred
+    }
+    /// Sends key input to the focused widget, unless it moves the focus or activates a
+    /// widget. Returns what the widgets deferred, like [`WidgetTree::pointer_event`].
+    #[must_use]
+    pub fn key_event(&mut self, evt: KeyEvent) -> Vec<Deferred> {
+        let mut deferred = Vec::new();
+        if self.focus_key(&evt, &mut deferred) {
+            return deferred;
+        }
+        if let Some(focused) = self.focused {
+            self.send(focused, WidgetEvent::Key(evt), &mut deferred);
+        }
+        deferred
+    }
+    /// Lets the focused widget react to what the input method did to it
+    #[must_use]
+    pub fn input_method_event(&mut self) -> Vec<Deferred> {
+        let mut deferred = Vec::new();
+        if let Some(focused) = self.focused {
+            self.send(focused, WidgetEvent::InputMethod, &mut deferred);
+        }
+        deferred
+    }
+    /// Input method client of the focused widget
+    pub fn text_input_client(&self) -> Option<Rc<RefCell<dyn TextInputClient>>> {
+        self.node(self.focused?).widget.text_input_client()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hit_test_finds_the_deepest_widget() {
+        let mut tree = WidgetTree::new(Container::new(), Layout::row());
+        let root = tree.root();
+        let padded = Layout::row().fixed(50, 50).padding(Insets::new(10, 10, 10, 10));
+        let child = tree.add(root, Container::new(), padded);
+        let grandchild = tree.add(child, Container::new(), Layout::default().fixed(20, 20));
+        tree.resize(100, 100);
+        tree.update_layout();
+        assert_eq!(tree.hit_test(15.0, 15.0), Some(grandchild));
+        assert_eq!(tree.hit_test(40.0, 40.0), Some(child));
+        assert_eq!(tree.hit_test(75.0, 25.0), Some(root));
+        assert_eq!(tree.hit_test(100.5, 25.0), None);
+        assert_eq!(tree.hit_test(-1.0, 25.0), None);
+    }
+
+    #[test]
+    fn hit_test_picks_the_top_of_a_stack() {
+        let mut tree = WidgetTree::new(Container::new(), Layout::stack().align(Align::Start));
+        let root = tree.root();
+        let below = tree.add(root, Container::new(), Layout::default().fixed(60, 60));
+        let above = tree.add(root, Container::new(), Layout::default().fixed(30, 30));
+        tree.resize(100, 100);
+        tree.update_layout();
+        assert_eq!(tree.hit_test(10.0, 10.0), Some(above));
+        assert_eq!(tree.hit_test(45.0, 45.0), Some(below));
+        tree.remove(above);
+        tree.update_layout();
+        assert_eq!(tree.hit_test(10.0, 10.0), Some(below));
+    }
+}
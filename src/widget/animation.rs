@@ -0,0 +1,401 @@
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+use rgb::RGBA8;
+
+use crate::prelude::*;
+
+use super::{Deferred, WidgetEvent, WidgetId, WidgetTree};
+
+// Springs are integrated in steps of at most this many seconds
+const SPRING_STEP: f32 = 1.0 / 240.0;
+// A spring closer than this to its target, and slower, is at rest. In units of the distance
+// it travels.
+const REST_DISTANCE: f32 = 0.001;
+const REST_VELOCITY: f32 = 0.01;
+// Longer gaps between frames, like while the window is hidden, don't make springs jump
+const MAX_FRAME_GAP: f32 = 0.1;
+
+thread_local! {
+    static REDUCE_MOTION: Cell<bool> = Cell::new(false);
+}
+
+/// Makes animations jump to their target instead, for users sensitive to motion
+pub fn set_reduce_motion(reduce: bool) {
+    REDUCE_MOTION.with(|reduce_motion| reduce_motion.set(reduce));
+}
+
+pub fn reduce_motion() -> bool {
+    REDUCE_MOTION.with(Cell::get)
+}
+
+/// How the progress of a tween follows time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Easing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+    /// Like CSS `cubic-bezier(x1, y1, x2, y2)`, both x between 0 and 1
+    CubicBezier(f32, f32, f32, f32),
+}
+
+impl Easing {
+    /// Progress at `t`, the fraction of the duration elapsed
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match *self {
+            Self::Linear => t,
+            Self::EaseIn => cubic_bezier(0.42, 0.0, 1.0, 1.0, t),
+            Self::EaseOut => cubic_bezier(0.0, 0.0, 0.58, 1.0, t),
+            Self::EaseInOut => cubic_bezier(0.42, 0.0, 0.58, 1.0, t),
+            Self::CubicBezier(x1, y1, x2, y2) => cubic_bezier(x1, y1, x2, y2, t),
+        }
+    }
+}
+
+/// y of the curve from (0, 0) to (1, 1) where it reaches `x`
+fn cubic_bezier(x1: f32, y1: f32, x2: f32, y2: f32, x: f32) -> f32 {
+    let curve = |p1: f32, p2: f32, t: f32| {
+        let u = 1.0 - t;
+        3.0 * u * u * t * p1 + 3.0 * u * t * t * p2 + t * t * t
+    };
+    // x only grows along the curve, so it can be bisected
+    let (mut low, mut high) = (0.0, 1.0);
+    for _ in 0..24 {
+        let mid = (low + high) / 2.0;
+        if curve(x1, x2, mid) < x {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    curve(y1, y2, (low + high) / 2.0)
+}
+
+/// A damped spring pulling the value to its target
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Spring {
+    pub stiffness: f32,
+    pub damping: f32,
+    pub mass: f32,
+}
+
+impl Spring {
+    /// Settles quickly without overshooting
+    pub const SMOOTH: Self = Self {
+        stiffness: 300.0,
+        damping: 35.0,
+        mass: 1.0,
+    };
+    /// Overshoots and bounces back a few times
+    pub const BOUNCY: Self = Self {
+        stiffness: 300.0,
+        damping: 12.0,
+        mass: 1.0,
+    };
+    /// Moves `progress` towards 1
+    fn step(&self, progress: &mut f32, velocity: &mut f32, dt: f32) {
+        let force = -self.stiffness * (*progress - 1.0) - self.damping * *velocity;
+        *velocity += force / self.mass * dt;
+        *progress += *velocity * dt;
+    }
+}
+
+/// How an [`Animation`] gets to its target
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Transition {
+    Tween { duration: Duration, easing: Easing },
+    Spring(Spring),
+}
+
+impl Default for Transition {
+    fn default() -> Self {
+        Self::tween(200, Easing::EaseOut)
+    }
+}
+
+impl Transition {
+    pub fn tween(millis: u64, easing: Easing) -> Self {
+        Self::Tween {
+            duration: Duration::from_millis(millis),
+            easing,
+        }
+    }
+}
+
+/// A value that can be animated
+pub trait Animatable: Clone + PartialEq {
+    /// `t` is 0 at `self` and 1 at `to`. Springs overshoot it a bit.
+    fn lerp(&self, to: &Self, t: f32) -> Self;
+    /// How far apart they are, to keep the speed when an animation is retargeted
+    fn distance(&self, other: &Self) -> f32;
+}
+
+impl Animatable for f32 {
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        self + (to - self) * t
+    }
+    fn distance(&self, other: &Self) -> f32 {
+        (other - self).abs()
+    }
+}
+
+impl Animatable for f64 {
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        self + (to - self) * t as f64
+    }
+    fn distance(&self, other: &Self) -> f32 {
+        (other - self).abs() as f32
+    }
+}
+
+impl Animatable for (f32, f32) {
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        (self.0.lerp(&to.0, t), self.1.lerp(&to.1, t))
+    }
+    fn distance(&self, other: &Self) -> f32 {
+        (other.0 - self.0).hypot(other.1 - self.1)
+    }
+}
+
+impl Animatable for RGBA8 {
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        let channel = |from: u8, to: u8| (from as f32).lerp(&(to as f32), t).round().clamp(0.0, 255.0) as u8;
+        RGBA8::new(
+            channel(self.r, to.r),
+            channel(self.g, to.g),
+            channel(self.b, to.b),
+            channel(self.a, to.a),
+        )
+    }
+    fn distance(&self, other: &Self) -> f32 {
+        let channel = |a: u8, b: u8| (a as f32 - b as f32).powi(2);
+        let sum = channel(self.r, other.r) + channel(self.g, other.g) + channel(self.b, other.b);
+        (sum + channel(self.a, other.a)).sqrt()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Running {
+    start: Instant,
+    last_frame: Instant,
+    /// From 0 at the start to 1 at the target
+    progress: f32,
+    /// Of `progress`, per second
+    velocity: f32,
+}
+
+/// A value moving to a target over time. It advances when [`Animation::tick`] is called,
+/// usually on each frame.
+#[derive(Debug, Clone)]
+pub struct Animation<T> {
+    from: T,
+    to: T,
+    transition: Transition,
+    /// `None` at rest
+    running: Option<Running>,
+}
+
+impl<T: Animatable> Animation<T> {
+    /// At rest at `value`
+    pub fn new(value: T, transition: Transition) -> Self {
+        Self {
+            from: value.clone(),
+            to: value,
+            transition,
+            running: None,
+        }
+    }
+    pub fn value(&self) -> T {
+        match &self.running {
+            Some(running) => self.from.lerp(&self.to, running.progress),
+            None => self.to.clone(),
+        }
+    }
+    pub fn target(&self) -> &T {
+        &self.to
+    }
+    pub fn is_running(&self) -> bool {
+        self.running.is_some()
+    }
+    /// Takes over the running animation from where it is
+    pub fn set_transition(&mut self, transition: Transition) {
+        self.transition = transition;
+    }
+    /// Jumps to `value`, stopping the animation
+    pub fn set(&mut self, value: T) {
+        self.from = value.clone();
+        self.to = value;
+        self.running = None;
+    }
+    /// Stops where it is
+    pub fn cancel(&mut self) {
+        self.set(self.value());
+    }
+    /// Moves from the current value to `to`, keeping the speed of the running animation.
+    /// Jumps there if motion is reduced.
+    pub fn animate_to(&mut self, to: T, now: Instant) {
+        if to == self.to && self.is_running() {
+            return;
+        }
+        let current = self.value();
+        if reduce_motion() || current == to {
+            self.set(to);
+            return;
+        }
+        // The velocity is relative to the distance traveled
+        let velocity = match self.running {
+            Some(running) => {
+                let (old, new) = (self.from.distance(&self.to), current.distance(&to));
+                if new > 0.0 { running.velocity * old / new } else { 0.0 }
+            }
+            None => 0.0,
+        };
+        self.from = current;
+        self.to = to;
+        self.running = Some(Running {
+            start: now,
+            last_frame: now,
+            progress: 0.0,
+            velocity,
+        });
+    }
+    /// Advances to `now`. Returns whether it is still running.
+    pub fn tick(&mut self, now: Instant) -> bool {
+        let Some(running) = &mut self.running else {
+            return false;
+        };
+        let dt = now.saturating_duration_since(running.last_frame).as_secs_f32();
+        running.last_frame = now;
+        let done = match self.transition {
+            _ if reduce_motion() => true,
+            Transition::Tween { duration, easing } => {
+                let elapsed = now.saturating_duration_since(running.start).as_secs_f32();
+                let t = elapsed / duration.as_secs_f32().max(f32::EPSILON);
+                let progress = easing.apply(t);
+                if dt > 0.0 {
+                    running.velocity = (progress - running.progress) / dt;
+                }
+                running.progress = progress;
+                t >= 1.0
+            }
+            Transition::Spring(spring) => {
+                let mut left = dt.min(MAX_FRAME_GAP);
+                while left > 0.0 {
+                    let step = left.min(SPRING_STEP);
+                    spring.step(&mut running.progress, &mut running.velocity, step);
+                    left -= step;
+                }
+                (1.0 - running.progress).abs() < REST_DISTANCE && running.velocity.abs() < REST_VELOCITY
+            }
+        };
+        if done {
+            self.set(self.to.clone());
+        }
+        !done
+    }
+}
+
+impl WidgetTree {
+    /// Transition of the opacity and offset animations of `id`
+    pub fn set_transition(&mut self, id: WidgetId, transition: Transition) {
+        let node = self.node_mut(id);
+        node.opacity.set_transition(transition);
+        node.offset.set_transition(transition);
+    }
+    pub fn opacity(&self, id: WidgetId) -> f32 {
+        self.node(id).opacity.value()
+    }
+    /// How opaque `id` and its children are drawn, from 0 to 1
+    pub fn set_opacity(&mut self, id: WidgetId, opacity: f32) {
+        self.node_mut(id).opacity.set(opacity.clamp(0.0, 1.0));
+        self.damage_subtree(id);
+    }
+    /// Fades `id` and its children to `opacity`
+    pub fn animate_opacity(&mut self, id: WidgetId, opacity: f32) {
+        self.node_mut(id).opacity.animate_to(opacity.clamp(0.0, 1.0), Instant::now());
+        self.damage_subtree(id);
+    }
+    pub fn offset(&self, id: WidgetId) -> (f32, f32) {
+        self.node(id).offset.value()
+    }
+    /// Moves `id` and its children away from where the layout put them, by `offset` pixels
+    pub fn set_offset(&mut self, id: WidgetId, offset: (f32, f32)) {
+        self.node_mut(id).offset.set(offset);
+        self.apply_offset(id);
+    }
+    /// Slides `id` and its children to `offset`
+    pub fn animate_offset(&mut self, id: WidgetId, offset: (f32, f32)) {
+        self.node_mut(id).offset.animate_to(offset, Instant::now());
+        self.damage_subtree(id);
+    }
+    /// Stops the opacity and offset of `id` where they are
+    pub fn cancel_animations(&mut self, id: WidgetId) {
+        let node = self.node_mut(id);
+        node.opacity.cancel();
+        node.offset.cancel();
+    }
+    /// Whether the window should draw another frame for the animations
+    pub fn is_animating(&self) -> bool {
+        !self.animating.is_empty()
+            || self.nodes.iter().flatten().any(|node| node.opacity.is_running() || node.offset.is_running())
+    }
+    /// Advances the animations to `now`, on each frame while [`WidgetTree::is_animating`].
+    /// Returns what the widgets deferred, like [`WidgetTree::pointer_event`].
+    #[must_use]
+    pub fn animation_frame(&mut self, now: Instant) -> Vec<Deferred> {
+        let mut deferred = Vec::new();
+        for i in 0..self.nodes.len() {
+            let id = WidgetId(i);
+            let Some(node) = self.nodes[i].as_mut() else {
+                continue;
+            };
+            let (fading, sliding) = (node.opacity.is_running(), node.offset.is_running());
+            node.opacity.tick(now);
+            node.offset.tick(now);
+            if fading {
+                self.damage_subtree(id);
+            }
+            if sliding {
+                self.apply_offset(id);
+            }
+        }
+        // Widgets ask again for each frame they want
+        for id in std::mem::take(&mut self.animating) {
+            self.send(id, WidgetEvent::AnimationFrame(now), &mut deferred);
+        }
+        deferred
+    }
+    /// Moves the rectangles of `id` and its children to its current offset
+    pub(super) fn apply_offset(&mut self, id: WidgetId) {
+        let node = self.node_mut(id);
+        let (x, y) = node.offset.value();
+        let offset = (x.round() as i32, y.round() as i32);
+        let (dx, dy) = (offset.0 - node.applied_offset.0, offset.1 - node.applied_offset.1);
+        node.applied_offset = offset;
+        if (dx, dy) == (0, 0) {
+            return;
+        }
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            let node = self.node_mut(id);
+            let old = node.rect;
+            node.rect = Rect::new(old.x + dx, old.y + dy, old.width, old.height);
+            let new = node.rect;
+            stack.extend(node.children.iter().copied());
+            self.damage.add(old);
+            self.damage.add(new);
+        }
+        // Widgets may have moved under the pointer
+        self.hovered = self.hit_test(self.pointer_pos.0, self.pointer_pos.1);
+    }
+    /// Draws `id` and its children again, they may reach outside of it
+    fn damage_subtree(&mut self, id: WidgetId) {
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            self.damage_widget(id);
+            stack.extend(self.node(id).children.iter().copied());
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use std::rc::Rc;
+use std::time::Instant;
+
+use crate::keyboard::keysyms;
+use crate::prelude::*;
+use crate::renderer::{text_buffer, text_size};
+
+use super::animation::Animatable;
+use super::{
+    Animation, ClickCb, DrawCtx, EventCtx, MnemonicText, Theme, Transition, Widget, WidgetEvent, BTN_LEFT,
+};
+
+const PADDING: Insets = Insets::symmetric(6, 16);
+
+/// Push button with a text label, activated when the click is released over it, or with
+/// Space or Enter while focused
+#[derive(Clone)]
+pub struct Button {
+    label: MnemonicText,
+    on_click: Option<Rc<dyn ClickCb>>,
+    /// Fades the face in and out of the hover color
+    hover: Animation<f32>,
+}
+
+impl std::fmt::Debug for Button {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct(std::any::type_name::<Self>())
+            .field("label", &self.label)
+            .field("on_click", &"Cool closure")
+            .field("hover", &self.hover)
+            .finish()
+    }
+}
+
+impl Button {
+    /// An underscore in `label` marks the mnemonic, see [`MnemonicText`]
+    pub fn new(label: &str) -> Self {
+        Self {
+            label: MnemonicText::parse(label),
+            on_click: None,
+            hover: Animation::new(0.0, Transition::default()),
+        }
+    }
+    pub fn on_click(mut self, on_click: impl ClickCb + 'static) -> Self {
+        self.on_click = Some(Rc::new(on_click));
+        self
+    }
+    pub fn label(&self) -> &str {
+        self.label.text()
+    }
+    pub fn set_label(&mut self, label: &str) {
+        self.label = MnemonicText::parse(label);
+    }
+    fn animate_hover(&mut self, hovered: bool, ctx: &mut EventCtx) {
+        self.hover.animate_to(if hovered { 1.0 } else { 0.0 }, Instant::now());
+        ctx.request_animation_frame();
+    }
+    /// Runs the click handler, once the event is handled
+    pub fn activate(&self, ctx: &mut EventCtx) {
+        if let Some(on_click) = self.on_click.clone() {
+            ctx.defer(move || on_click());
+        }
+    }
+}
+
+impl Widget for Button {
+    fn measure(&mut self, theme: &Theme) -> Size {
+        let text = text_size(&mut text_buffer(theme.metrics(), self.label.text()));
+        Size::new(text.width + PADDING.horizontal(), text.height + PADDING.vertical())
+    }
+    fn draw(&mut self, ctx: &mut DrawCtx) {
+        let (theme, state) = (ctx.theme, ctx.state);
+        let face = match state {
+            _ if state.disabled => theme.surface,
+            _ if state.pressed => theme.surface_pressed,
+            _ => theme.surface.lerp(&theme.surface_hover, self.hover.value()),
+        };
+        ctx.canvas.fill_rect(ctx.rect, face);
+        ctx.canvas.stroke_rect(ctx.rect, 1, theme.border);
+        ctx.centered_label(ctx.rect.inset(PADDING), &self.label, theme.text_color(state.disabled));
+    }
+    fn focusable(&self) -> bool {
+        true
+    }
+    fn event(&mut self, evt: WidgetEvent, ctx: &mut EventCtx) {
+        match evt {
+            WidgetEvent::PointerEnter => self.animate_hover(true, ctx),
+            WidgetEvent::PointerLeave => self.animate_hover(false, ctx),
+            WidgetEvent::AnimationFrame(now) => {
+                if self.hover.tick(now) {
+                    ctx.request_animation_frame();
+                }
+                ctx.request_redraw();
+            }
+            WidgetEvent::PointerButton { button: BTN_LEFT, pressed: false, .. } if ctx.state.hovered => {
+                self.activate(ctx)
+            }
+            WidgetEvent::Key(key)
+                if key.pressed
+                    && matches!(
+                        key.keysym,
+                        keysyms::XKB_KEY_space | keysyms::XKB_KEY_Return | keysyms::XKB_KEY_KP_Enter
+                    ) =>
+            {
+                self.activate(ctx)
+            }
+            WidgetEvent::Activate => self.activate(ctx),
+            _ => (),
+        }
+    }
+    fn mnemonic(&self) -> Option<char> {
+        self.label.key()
+    }
+}
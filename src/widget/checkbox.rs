@@ -0,0 +1,108 @@
+use std::rc::Rc;
+
+use crate::keyboard::keysyms;
+use crate::prelude::*;
+use crate::renderer::{text_buffer, text_size};
+
+use super::animation::Animatable;
+use super::{DrawCtx, EventCtx, MnemonicText, Theme, ToggleCb, Widget, WidgetEvent, BTN_LEFT};
+
+const BOX_SIZE: i32 = 16;
+const SPACING: i32 = 8;
+
+/// Box that can be checked, followed by a text label. Space toggles it while focused.
+#[derive(Clone)]
+pub struct Checkbox {
+    label: MnemonicText,
+    checked: bool,
+    on_toggle: Option<Rc<dyn ToggleCb>>,
+}
+
+impl std::fmt::Debug for Checkbox {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct(std::any::type_name::<Self>())
+            .field("label", &self.label)
+            .field("checked", &self.checked)
+            .field("on_toggle", &"Cool closure")
+            .finish()
+    }
+}
+
+impl Checkbox {
+    /// An underscore in `label` marks the mnemonic, see [`MnemonicText`]
+    pub fn new(label: &str, checked: bool) -> Self {
+        Self {
+            label: MnemonicText::parse(label),
+            checked,
+            on_toggle: None,
+        }
+    }
+    /// Called with the new value when the user toggles it
+    pub fn on_toggle(mut self, on_toggle: impl ToggleCb + 'static) -> Self {
+        self.on_toggle = Some(Rc::new(on_toggle));
+        self
+    }
+    pub fn is_checked(&self) -> bool {
+        self.checked
+    }
+    /// Doesn't call the toggle handler
+    pub fn set_checked(&mut self, checked: bool) {
+        self.checked = checked;
+    }
+    pub fn toggle(&mut self, ctx: &mut EventCtx) {
+        self.checked = !self.checked;
+        ctx.request_redraw();
+        if let Some(on_toggle) = self.on_toggle.clone() {
+            let checked = self.checked;
+            ctx.defer(move || on_toggle(checked));
+        }
+    }
+}
+
+impl Widget for Checkbox {
+    fn measure(&mut self, theme: &Theme) -> Size {
+        let text = text_size(&mut text_buffer(theme.metrics(), self.label.text()));
+        Size::new(BOX_SIZE + SPACING + text.width, BOX_SIZE.max(text.height))
+    }
+    fn draw(&mut self, ctx: &mut DrawCtx) {
+        let (theme, state, rect) = (ctx.theme, ctx.state, ctx.rect);
+        let check_box = Rect::new(rect.x, rect.y + (rect.height - BOX_SIZE) / 2, BOX_SIZE, BOX_SIZE);
+        if self.checked {
+            let fill = match state {
+                _ if state.disabled => theme.text_disabled,
+                _ if state.pressed => theme.accent.lerp(&theme.surface_pressed, 0.5),
+                _ if state.hovered => theme.accent_hover,
+                _ => theme.accent,
+            };
+            ctx.canvas.fill_rect(check_box, fill);
+            ctx.canvas.fill_rect(check_box.inset(Insets::uniform(4)), theme.on_accent);
+        } else {
+            let fill = match state {
+                _ if state.disabled => theme.surface,
+                _ if state.pressed => theme.surface_pressed,
+                _ if state.hovered => theme.surface_hover,
+                _ => theme.surface,
+            };
+            ctx.canvas.fill_rect(check_box, fill);
+            ctx.canvas.stroke_rect(check_box, 1, theme.border);
+        }
+        let label = Rect::new(rect.x + BOX_SIZE + SPACING, rect.y, rect.width - BOX_SIZE - SPACING, rect.height);
+        ctx.label(label, &self.label, theme.text_color(state.disabled));
+    }
+    fn focusable(&self) -> bool {
+        true
+    }
+    fn event(&mut self, evt: WidgetEvent, ctx: &mut EventCtx) {
+        match evt {
+            WidgetEvent::PointerButton { button: BTN_LEFT, pressed: false, .. } if ctx.state.hovered => {
+                self.toggle(ctx)
+            }
+            WidgetEvent::Key(key) if key.pressed && key.keysym == keysyms::XKB_KEY_space => self.toggle(ctx),
+            WidgetEvent::Activate => self.toggle(ctx),
+            _ => (),
+        }
+    }
+    fn mnemonic(&self) -> Option<char> {
+        self.label.key()
+    }
+}
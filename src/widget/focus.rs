@@ -0,0 +1,313 @@
+use std::ops::Range;
+
+use crate::keyboard::{keysyms, KeyEvent};
+
+use super::{Deferred, WidgetEvent, WidgetId, WidgetTree};
+
+/// Key combination activating a widget from anywhere in the window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Accelerator {
+    keysym: u32,
+    ctrl: bool,
+    alt: bool,
+    shift: bool,
+    logo: bool,
+}
+
+impl Accelerator {
+    /// `keysym` is one of [`keysyms`], letters match in either case
+    pub fn new(keysym: u32) -> Self {
+        Self {
+            keysym,
+            ctrl: false,
+            alt: false,
+            shift: false,
+            logo: false,
+        }
+    }
+    pub fn ctrl(mut self) -> Self {
+        self.ctrl = true;
+        self
+    }
+    pub fn alt(mut self) -> Self {
+        self.alt = true;
+        self
+    }
+    pub fn shift(mut self) -> Self {
+        self.shift = true;
+        self
+    }
+    pub fn logo(mut self) -> Self {
+        self.logo = true;
+        self
+    }
+    fn matches(&self, evt: &KeyEvent) -> bool {
+        let modifiers = evt.modifiers;
+        fold_case(self.keysym) == fold_case(evt.keysym)
+            && self.ctrl == modifiers.ctrl
+            && self.alt == modifiers.alt
+            && self.shift == modifiers.shift
+            && self.logo == modifiers.logo
+    }
+}
+
+/// Lower case keysym of latin letters, which the keymap gives in upper case with Shift
+fn fold_case(keysym: u32) -> u32 {
+    match keysym {
+        keysyms::XKB_KEY_A..=keysyms::XKB_KEY_Z => keysym - keysyms::XKB_KEY_A + keysyms::XKB_KEY_a,
+        _ => keysym,
+    }
+}
+
+/// A label with a mnemonic, the character following `_`. Alt and that character activate
+/// the widget. `__` stands for an underscore.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MnemonicText {
+    text: String,
+    /// Byte offset in `text` and character of the mnemonic
+    mnemonic: Option<(usize, char)>,
+}
+
+impl MnemonicText {
+    pub fn parse(label: &str) -> Self {
+        let mut text = String::with_capacity(label.len());
+        let mut mnemonic = None;
+        let mut chars = label.chars();
+        while let Some(c) = chars.next() {
+            if c != '_' {
+                text.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('_') => text.push('_'),
+                Some(next) => {
+                    mnemonic = mnemonic.or(Some((text.len(), next)));
+                    text.push(next);
+                }
+                None => (),
+            }
+        }
+        Self { text, mnemonic }
+    }
+    /// The label without the markers
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+    /// Lower case character activating the widget
+    pub fn key(&self) -> Option<char> {
+        self.mnemonic.and_then(|(_, c)| c.to_lowercase().next())
+    }
+    /// Bytes of `text` to underline while mnemonics are shown
+    pub fn range(&self) -> Option<Range<usize>> {
+        self.mnemonic.map(|(at, c)| at..at + c.len_utf8())
+    }
+}
+
+impl WidgetTree {
+    /// Focusable, enabled widgets in the order Tab goes through them: depth first, in the
+    /// order they were added
+    pub fn focus_chain(&self) -> Vec<WidgetId> {
+        let mut chain = Vec::new();
+        let mut stack = vec![self.root];
+        while let Some(id) = stack.pop() {
+            let node = self.node(id);
+            // Children of disabled widgets are disabled too
+            if node.disabled {
+                continue;
+            }
+            if node.widget.focusable() {
+                chain.push(id);
+            }
+            stack.extend(node.children.iter().rev());
+        }
+        chain
+    }
+    /// Moves the focus to `id`, or clears it. Returns what the widgets deferred, like
+    /// [`WidgetTree::pointer_event`].
+    #[must_use]
+    pub fn set_focus(&mut self, id: Option<WidgetId>) -> Vec<Deferred> {
+        let mut deferred = Vec::new();
+        self.focus(id, &mut deferred);
+        deferred
+    }
+    #[must_use]
+    pub fn focus_next(&mut self) -> Vec<Deferred> {
+        let mut deferred = Vec::new();
+        self.step_focus(true, &mut deferred);
+        deferred
+    }
+    #[must_use]
+    pub fn focus_previous(&mut self) -> Vec<Deferred> {
+        let mut deferred = Vec::new();
+        self.step_focus(false, &mut deferred);
+        deferred
+    }
+    /// Activates `id` when the keys of `accelerator` are pressed, `None` removes it
+    pub fn set_accelerator(&mut self, id: WidgetId, accelerator: Option<Accelerator>) {
+        self.node_mut(id).accelerator = accelerator;
+    }
+    /// Whether the window has the keyboard focus
+    pub fn has_keyboard_focus(&self) -> bool {
+        self.keyboard_focus
+    }
+    /// The window got the keyboard focus back, and with it the widget that had it last
+    #[must_use]
+    pub fn keyboard_enter(&mut self) -> Vec<Deferred> {
+        let mut deferred = Vec::new();
+        self.keyboard_focus = true;
+        match self.focused {
+            Some(focused) if self.is_disabled(focused) => self.focused = None,
+            Some(focused) => {
+                self.damage_widget(focused);
+                self.send(focused, WidgetEvent::FocusIn, &mut deferred);
+            }
+            None => (),
+        }
+        deferred
+    }
+    /// The window lost the keyboard focus. The focused widget is remembered.
+    #[must_use]
+    pub fn keyboard_leave(&mut self) -> Vec<Deferred> {
+        let mut deferred = Vec::new();
+        if let Some(focused) = self.focused {
+            self.damage_widget(focused);
+            self.send(focused, WidgetEvent::FocusOut, &mut deferred);
+        }
+        self.keyboard_focus = false;
+        self.set_show_mnemonics(false);
+        deferred
+    }
+    pub(super) fn focus(&mut self, id: Option<WidgetId>, deferred: &mut Vec<Deferred>) {
+        if id == self.focused {
+            return;
+        }
+        let old = std::mem::replace(&mut self.focused, id);
+        for changed in old.iter().chain(&id) {
+            self.damage_widget(*changed);
+        }
+        if !self.keyboard_focus {
+            return;
+        }
+        if let Some(old) = old {
+            self.send(old, WidgetEvent::FocusOut, deferred);
+        }
+        if let Some(id) = id {
+            self.send(id, WidgetEvent::FocusIn, deferred);
+        }
+    }
+    /// Shows or hides the focus ring
+    pub(super) fn set_focus_visible(&mut self, visible: bool) {
+        if visible == self.focus_visible {
+            return;
+        }
+        self.focus_visible = visible;
+        if let Some(focused) = self.focused {
+            self.damage_widget(focused);
+        }
+    }
+    /// Shows or hides the underlines of the mnemonics
+    fn set_show_mnemonics(&mut self, show: bool) {
+        if show == self.show_mnemonics {
+            return;
+        }
+        self.show_mnemonics = show;
+        let labeled: Vec<WidgetId> = (0..self.nodes.len())
+            .map(WidgetId)
+            .filter(|id| self.contains(*id) && self.node(*id).widget.mnemonic().is_some())
+            .collect();
+        for id in labeled {
+            self.damage_widget(id);
+        }
+    }
+    fn step_focus(&mut self, forward: bool, deferred: &mut Vec<Deferred>) {
+        let chain = self.focus_chain();
+        let len = chain.len();
+        if len == 0 {
+            return;
+        }
+        let current = self.focused.and_then(|focused| chain.iter().position(|id| *id == focused));
+        let next = match current {
+            Some(i) if forward => chain[(i + 1) % len],
+            Some(i) => chain[(i + len - 1) % len],
+            None if forward => chain[0],
+            None => chain[len - 1],
+        };
+        self.set_focus_visible(true);
+        self.focus(Some(next), deferred);
+    }
+    /// Focuses `id` if it can be, and activates it
+    fn activate(&mut self, id: WidgetId, deferred: &mut Vec<Deferred>) {
+        if self.node(id).widget.focusable() {
+            self.focus(Some(id), deferred);
+        }
+        self.send(id, WidgetEvent::Activate, deferred);
+    }
+    /// The widget with `key` as mnemonic after the focused one, and whether it is the only one
+    fn mnemonic_target(&self, key: char) -> Option<(WidgetId, bool)> {
+        let chain = self.focus_chain();
+        let matching: Vec<WidgetId> = chain
+            .iter()
+            .copied()
+            .filter(|id| self.node(*id).widget.mnemonic() == Some(key))
+            .collect();
+        let current = self.focused.and_then(|focused| chain.iter().position(|id| *id == focused));
+        let position = |id: &WidgetId| chain.iter().position(|chained| chained == id);
+        let next = matching
+            .iter()
+            .find(|id| current.is_some_and(|current| position(id) > Some(current)))
+            .or(matching.first())?;
+        Some((*next, matching.len() == 1))
+    }
+    fn accelerator_target(&self, evt: &KeyEvent) -> Option<WidgetId> {
+        let id = self.nodes.iter().enumerate().find_map(|(i, node)| {
+            let accelerator = node.as_ref()?.accelerator?;
+            accelerator.matches(evt).then_some(WidgetId(i))
+        })?;
+        (!self.is_disabled(id)).then_some(id)
+    }
+    /// Handles the keys moving the focus, before the focused widget gets them. Returns
+    /// whether `evt` was used.
+    pub(super) fn focus_key(&mut self, evt: &KeyEvent, deferred: &mut Vec<Deferred>) -> bool {
+        if matches!(evt.keysym, keysyms::XKB_KEY_Alt_L | keysyms::XKB_KEY_Alt_R) {
+            self.set_show_mnemonics(evt.pressed);
+            return false;
+        }
+        if !evt.pressed {
+            return false;
+        }
+        if let Some(id) = self.accelerator_target(evt) {
+            self.activate(id, deferred);
+            return true;
+        }
+        let modifiers = evt.modifiers;
+        let is_tab = matches!(
+            evt.keysym,
+            keysyms::XKB_KEY_Tab | keysyms::XKB_KEY_KP_Tab | keysyms::XKB_KEY_ISO_Left_Tab
+        );
+        if is_tab && !modifiers.ctrl && !modifiers.alt && !modifiers.logo {
+            let backwards = modifiers.shift || evt.keysym == keysyms::XKB_KEY_ISO_Left_Tab;
+            self.step_focus(!backwards, deferred);
+            return true;
+        }
+        if !modifiers.alt || modifiers.ctrl || modifiers.logo {
+            return false;
+        }
+        let key = evt
+            .utf8
+            .as_deref()
+            .and_then(|text| text.chars().next())
+            .or_else(|| char::from_u32(fold_case(evt.keysym)).filter(|_| evt.keysym < 0x100))
+            .and_then(|c| c.to_lowercase().next());
+        let Some((id, unique)) = key.and_then(|key| self.mnemonic_target(key)) else {
+            return false;
+        };
+        self.set_focus_visible(true);
+        // With several widgets on the same key, each press moves to the next one
+        if unique {
+            self.activate(id, deferred);
+        } else {
+            self.focus(Some(id), deferred);
+        }
+        true
+    }
+}
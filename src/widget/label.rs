@@ -0,0 +1,46 @@
+use rgb::RGBA8;
+
+use crate::prelude::*;
+use crate::renderer::{text_buffer, text_size};
+
+use super::{DrawCtx, Theme, Widget};
+
+/// A single line of text
+#[derive(Debug, Clone)]
+pub struct Label {
+    text: String,
+    color: Option<RGBA8>,
+}
+
+impl Label {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            color: None,
+        }
+    }
+    /// Used instead of the theme's text color, unless disabled
+    pub fn color(mut self, color: RGBA8) -> Self {
+        self.color = Some(color);
+        self
+    }
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+    pub fn set_text(&mut self, text: impl Into<String>) {
+        self.text = text.into();
+    }
+}
+
+impl Widget for Label {
+    fn measure(&mut self, theme: &Theme) -> Size {
+        text_size(&mut text_buffer(theme.metrics(), &self.text))
+    }
+    fn draw(&mut self, ctx: &mut DrawCtx) {
+        let color = match self.color {
+            Some(color) if !ctx.state.disabled => color,
+            _ => ctx.theme.text_color(ctx.state.disabled),
+        };
+        ctx.text(ctx.rect, &self.text, color);
+    }
+}
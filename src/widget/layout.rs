@@ -0,0 +1,372 @@
+use crate::prelude::*;
+
+use super::{WidgetId, WidgetTree};
+
+/// How a widget places its children
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Row,
+    Column,
+    /// On top of each other, the last one on top
+    Stack,
+}
+
+/// Placement on the cross axis, or on both axes in a stack
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    Start,
+    Center,
+    End,
+    Stretch,
+}
+
+/// Placement on the main axis of the space the children don't grow into
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Justify {
+    Start,
+    Center,
+    End,
+    SpaceBetween,
+}
+
+/// Layout properties of a widget, both as a child and as a parent
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Layout {
+    pub direction: Direction,
+    pub padding: Insets,
+    /// Space between children
+    pub gap: i32,
+    pub align: Align,
+    pub justify: Justify,
+    /// Overrides the parent's `align` for this widget
+    pub align_self: Option<Align>,
+    pub min: Size,
+    pub max: Size,
+    /// Share of the parent's free space on its main axis
+    pub grow: f32,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self {
+            direction: Direction::Column,
+            padding: Insets::default(),
+            gap: 0,
+            align: Align::Stretch,
+            justify: Justify::Start,
+            align_self: None,
+            min: Size::default(),
+            max: Size::MAX,
+            grow: 0.0,
+        }
+    }
+}
+
+impl Layout {
+    pub fn row() -> Self {
+        Self {
+            direction: Direction::Row,
+            ..Default::default()
+        }
+    }
+    pub fn column() -> Self {
+        Self::default()
+    }
+    pub fn stack() -> Self {
+        Self {
+            direction: Direction::Stack,
+            ..Default::default()
+        }
+    }
+    pub fn padding(mut self, padding: Insets) -> Self {
+        self.padding = padding;
+        self
+    }
+    pub fn gap(mut self, gap: i32) -> Self {
+        self.gap = gap;
+        self
+    }
+    pub fn align(mut self, align: Align) -> Self {
+        self.align = align;
+        self
+    }
+    pub fn justify(mut self, justify: Justify) -> Self {
+        self.justify = justify;
+        self
+    }
+    pub fn align_self(mut self, align: Align) -> Self {
+        self.align_self = Some(align);
+        self
+    }
+    pub fn min(mut self, width: i32, height: i32) -> Self {
+        self.min = Size::new(width, height);
+        self
+    }
+    pub fn max(mut self, width: i32, height: i32) -> Self {
+        self.max = Size::new(width, height);
+        self
+    }
+    /// Same min and max size
+    pub fn fixed(self, width: i32, height: i32) -> Self {
+        self.min(width, height).max(width, height)
+    }
+    pub fn grow(mut self, grow: f32) -> Self {
+        self.grow = grow;
+        self
+    }
+}
+
+/// Sizes along the main and cross axes of a row or column
+fn split(size: Size, direction: Direction) -> (i32, i32) {
+    match direction {
+        Direction::Row | Direction::Stack => (size.width, size.height),
+        Direction::Column => (size.height, size.width),
+    }
+}
+
+fn join(main: i32, cross: i32, direction: Direction) -> Size {
+    match direction {
+        Direction::Row | Direction::Stack => Size::new(main, cross),
+        Direction::Column => Size::new(cross, main),
+    }
+}
+
+/// Offset of something `size` long placed in `space` according to `align`
+fn align_offset(align: Align, space: i32, size: i32) -> i32 {
+    match align {
+        Align::Start | Align::Stretch => 0,
+        Align::Center => (space - size) / 2,
+        Align::End => space - size,
+    }
+}
+
+/// Computes the preferred size of `id` and its children, bottom up.
+pub(super) fn measure(tree: &mut WidgetTree, id: WidgetId) -> Size {
+    let children = tree.node(id).children.clone();
+    let direction = tree.node(id).layout.direction;
+    let (mut main, mut cross) = (0, 0);
+    for child in &children {
+        let (child_main, child_cross) = split(measure(tree, *child), direction);
+        match direction {
+            Direction::Stack => main = main.max(child_main),
+            Direction::Row | Direction::Column => main += child_main,
+        }
+        cross = cross.max(child_cross);
+    }
+    let node = tree.nodes[id.0].as_mut().expect("Widget was removed");
+    if direction != Direction::Stack && children.len() > 1 {
+        main += node.layout.gap * (children.len() as i32 - 1);
+    }
+    let content = node.widget.measure(&tree.theme);
+    let children = join(main, cross, direction);
+    let padding = node.layout.padding;
+    let size = Size::new(
+        content.width.max(children.width) + padding.horizontal(),
+        content.height.max(children.height) + padding.vertical(),
+    );
+    node.preferred = size.clamp(node.layout.min, node.layout.max);
+    node.preferred
+}
+
+/// Places `id` at `rect` and its children inside it, top down. [`measure`] must have run.
+pub(super) fn arrange(tree: &mut WidgetTree, id: WidgetId, rect: Rect) {
+    let node = tree.node_mut(id);
+    node.rect = rect;
+    let layout = node.layout;
+    let inner = rect.inset(layout.padding);
+    let children = node.children.clone();
+    if children.is_empty() {
+        return;
+    }
+
+    if layout.direction == Direction::Stack {
+        for child in children {
+            let child_layout = tree.node(child).layout;
+            let align = child_layout.align_self.unwrap_or(layout.align);
+            let size = match align {
+                Align::Stretch => Size::new(inner.width, inner.height),
+                _ => tree.node(child).preferred,
+            }
+            .clamp(child_layout.min, child_layout.max);
+            let x = inner.x + align_offset(align, inner.width, size.width);
+            let y = inner.y + align_offset(align, inner.height, size.height);
+            arrange(tree, child, Rect::new(x, y, size.width, size.height));
+        }
+        return;
+    }
+
+    let direction = layout.direction;
+    let (inner_main, inner_cross) = split(Size::new(inner.width, inner.height), direction);
+    let gaps = layout.gap * (children.len() as i32 - 1);
+    let mut sizes: Vec<i32> = children
+        .iter()
+        .map(|child| split(tree.node(*child).preferred, direction).0)
+        .collect();
+    let max_sizes: Vec<i32> = children
+        .iter()
+        .map(|child| split(tree.node(*child).layout.max, direction).0)
+        .collect();
+    let grows: Vec<f32> = children.iter().map(|child| tree.node(*child).layout.grow).collect();
+
+    // Hands out the free space by grow factor. Children hitting their max size give
+    // back what they can't take, so this runs until nothing changes.
+    let mut growing: Vec<bool> = grows.iter().map(|grow| *grow > 0.0).collect();
+    loop {
+        let free = inner_main - gaps - sizes.iter().sum::<i32>();
+        let total_grow: f32 = (0..children.len()).filter(|i| growing[*i]).map(|i| grows[i]).sum();
+        if free <= 0 || total_grow <= 0.0 {
+            break;
+        }
+        let mut handed_out = 0;
+        for i in 0..children.len() {
+            if !growing[i] {
+                continue;
+            }
+            let share = (free as f32 * grows[i] / total_grow) as i32;
+            let grown = (sizes[i] + share).min(max_sizes[i]);
+            if grown == max_sizes[i] {
+                growing[i] = false;
+            }
+            handed_out += grown - sizes[i];
+            sizes[i] = grown;
+        }
+        if handed_out == 0 {
+            // The shares rounded down to nothing, what is left is less than a pixel each
+            let mut left = free;
+            for i in 0..children.len() {
+                if left > 0 && growing[i] && sizes[i] < max_sizes[i] {
+                    sizes[i] += 1;
+                    left -= 1;
+                }
+            }
+            break;
+        }
+    }
+
+    let free = (inner_main - gaps - sizes.iter().sum::<i32>()).max(0);
+    let (mut pos, spacing) = match layout.justify {
+        Justify::Start => (0, layout.gap),
+        Justify::Center => (free / 2, layout.gap),
+        Justify::End => (free, layout.gap),
+        Justify::SpaceBetween if children.len() > 1 => (0, layout.gap + free / (children.len() as i32 - 1)),
+        Justify::SpaceBetween => (0, layout.gap),
+    };
+    for (child, main) in children.into_iter().zip(sizes) {
+        let child_layout = tree.node(child).layout;
+        let align = child_layout.align_self.unwrap_or(layout.align);
+        let (min_cross, max_cross) = (
+            split(child_layout.min, direction).1,
+            split(child_layout.max, direction).1,
+        );
+        let cross = match align {
+            Align::Stretch => inner_cross,
+            _ => split(tree.node(child).preferred, direction).1,
+        }
+        .min(max_cross)
+        .max(min_cross);
+        let cross_pos = align_offset(align, inner_cross, cross);
+        let rect = match direction {
+            Direction::Column => Rect::new(inner.x + cross_pos, inner.y + pos, cross, main),
+            _ => Rect::new(inner.x + pos, inner.y + cross_pos, main, cross),
+        };
+        arrange(tree, child, rect);
+        pos += main + spacing;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::widget::{Container, Theme, Widget};
+
+    /// Leaf of a fixed size
+    struct Fixed(Size);
+
+    impl Widget for Fixed {
+        fn measure(&mut self, _theme: &Theme) -> Size {
+            self.0
+        }
+    }
+
+    /// Lays out children of `layouts`, each 10x10, in a `width`x`height` root of `root`
+    fn arrange_children(root: Layout, layouts: &[Layout], width: i32, height: i32) -> Vec<Rect> {
+        let mut tree = WidgetTree::new(Container::new(), root);
+        let children: Vec<WidgetId> = layouts
+            .iter()
+            .map(|layout| tree.add(tree.root(), Fixed(Size::new(10, 10)), *layout))
+            .collect();
+        tree.resize(width, height);
+        tree.update_layout();
+        children.into_iter().map(|child| tree.rect(child)).collect()
+    }
+
+    #[test]
+    fn measure_adds_gaps_and_padding() {
+        let layout = Layout::column().gap(5).padding(Insets::new(1, 2, 3, 4));
+        let mut tree = WidgetTree::new(Container::new(), layout);
+        tree.add(tree.root(), Fixed(Size::new(30, 10)), Layout::default());
+        tree.add(tree.root(), Fixed(Size::new(20, 10)), Layout::default());
+        let small = tree.add(tree.root(), Fixed(Size::new(50, 10)), Layout::default().max(40, 5));
+        let root = tree.root();
+        assert_eq!(measure(&mut tree, root), Size::new(40 + 6, 10 + 10 + 5 + 5 * 2 + 4));
+        assert_eq!(tree.node(small).preferred, Size::new(40, 5));
+    }
+
+    #[test]
+    fn grow_shares_free_space() {
+        let layouts = [Layout::default().grow(1.0), Layout::default().grow(1.0), Layout::default().grow(2.0)];
+        let widths: Vec<i32> = arrange_children(Layout::row(), &layouts, 100, 10)
+            .iter()
+            .map(|rect| rect.width)
+            .collect();
+        assert_eq!(widths, [28, 27, 45]);
+    }
+
+    #[test]
+    fn grow_hands_out_the_remainder() {
+        let layouts = [Layout::default().grow(1.0); 3];
+        let rects = arrange_children(Layout::row(), &layouts, 32, 10);
+        let widths: Vec<i32> = rects.iter().map(|rect| rect.width).collect();
+        assert_eq!(widths, [11, 11, 10]);
+        let xs: Vec<i32> = rects.iter().map(|rect| rect.x).collect();
+        assert_eq!(xs, [0, 11, 22]);
+    }
+
+    #[test]
+    fn grow_stops_at_max() {
+        let layouts = [Layout::default().grow(1.0).max(20, 10), Layout::default().grow(1.0)];
+        let widths: Vec<i32> = arrange_children(Layout::row(), &layouts, 100, 10)
+            .iter()
+            .map(|rect| rect.width)
+            .collect();
+        assert_eq!(widths, [20, 80]);
+    }
+
+    #[test]
+    fn justify() {
+        let xs = |justify| -> Vec<i32> {
+            let layouts = [Layout::default(); 2];
+            let rects = arrange_children(Layout::row().gap(10).justify(justify), &layouts, 100, 10);
+            rects.iter().map(|rect| rect.x).collect()
+        };
+        assert_eq!(xs(Justify::Start), [0, 20]);
+        assert_eq!(xs(Justify::Center), [35, 55]);
+        assert_eq!(xs(Justify::End), [70, 90]);
+        assert_eq!(xs(Justify::SpaceBetween), [0, 90]);
+    }
+
+    #[test]
+    fn align() {
+        let layouts = [
+            Layout::default().align_self(Align::Start),
+            Layout::default().align_self(Align::Center),
+            Layout::default().align_self(Align::End),
+            Layout::default(),
+        ];
+        let rects = arrange_children(Layout::column().align(Align::Stretch), &layouts, 100, 40);
+        let cross: Vec<(i32, i32)> = rects.iter().map(|rect| (rect.x, rect.width)).collect();
+        assert_eq!(cross, [(0, 10), (45, 10), (90, 10), (0, 100)]);
+        let ys: Vec<i32> = rects.iter().map(|rect| rect.y).collect();
+        assert_eq!(ys, [0, 10, 20, 30]);
+    }
+}
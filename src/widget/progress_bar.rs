@@ -0,0 +1,40 @@
+use crate::prelude::*;
+
+use super::{DrawCtx, Theme, Widget};
+
+const HEIGHT: i32 = 8;
+
+/// Shows how much of a task is done
+#[derive(Debug, Clone, Default)]
+pub struct ProgressBar {
+    fraction: f64,
+}
+
+impl ProgressBar {
+    /// `fraction` goes from 0 to 1
+    pub fn new(fraction: f64) -> Self {
+        Self {
+            fraction: fraction.clamp(0.0, 1.0),
+        }
+    }
+    pub fn fraction(&self) -> f64 {
+        self.fraction
+    }
+    pub fn set_fraction(&mut self, fraction: f64) {
+        self.fraction = fraction.clamp(0.0, 1.0);
+    }
+}
+
+impl Widget for ProgressBar {
+    fn measure(&mut self, _theme: &Theme) -> Size {
+        Size::new(120, HEIGHT)
+    }
+    fn draw(&mut self, ctx: &mut DrawCtx) {
+        let (theme, rect) = (ctx.theme, ctx.rect);
+        let bar = Rect::new(rect.x, rect.y + (rect.height - HEIGHT) / 2, rect.width, HEIGHT);
+        ctx.canvas.fill_rect(bar, theme.surface_pressed);
+        let filled = (bar.width as f64 * self.fraction).round() as i32;
+        let fill = if ctx.state.disabled { theme.text_disabled } else { theme.accent };
+        ctx.canvas.fill_rect(Rect { width: filled, ..bar }, fill);
+    }
+}
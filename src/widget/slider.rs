@@ -0,0 +1,146 @@
+use std::ops::RangeInclusive;
+use std::rc::Rc;
+
+use crate::keyboard::keysyms;
+use crate::prelude::*;
+
+use super::{DrawCtx, EventCtx, Theme, ValueCb, Widget, WidgetEvent, BTN_LEFT};
+
+const KNOB_RADIUS: f32 = 8.0;
+const TRACK_HEIGHT: i32 = 4;
+
+/// Picks a value in a range by dragging a knob, or with the arrow keys while focused
+#[derive(Clone)]
+pub struct Slider {
+    value: f64,
+    range: RangeInclusive<f64>,
+    step: Option<f64>,
+    on_change: Option<Rc<dyn ValueCb>>,
+}
+
+impl std::fmt::Debug for Slider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct(std::any::type_name::<Self>())
+            .field("value", &self.value)
+            .field("range", &self.range)
+            .field("step", &self.step)
+            .field("on_change", &"Cool closure")
+            .finish()
+    }
+}
+
+impl Slider {
+    pub fn new(range: RangeInclusive<f64>, value: f64) -> Self {
+        Self {
+            value: value.clamp(*range.start(), *range.end()),
+            range,
+            step: None,
+            on_change: None,
+        }
+    }
+    /// Snaps values to multiples of `step` from the start of the range
+    pub fn step(mut self, step: f64) -> Self {
+        self.step = Some(step);
+        self
+    }
+    /// Called with every new value while the knob is dragged
+    pub fn on_change(mut self, on_change: impl ValueCb + 'static) -> Self {
+        self.on_change = Some(Rc::new(on_change));
+        self
+    }
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+    /// Doesn't call the change handler
+    pub fn set_value(&mut self, value: f64) {
+        self.value = self.snap(value);
+    }
+    fn snap(&self, value: f64) -> f64 {
+        let (start, end) = (*self.range.start(), *self.range.end());
+        let value = match self.step {
+            Some(step) if step > 0.0 => start + ((value - start) / step).round() * step,
+            _ => value,
+        };
+        value.clamp(start, end)
+    }
+    /// Position of the range in [0, 1]
+    fn fraction(&self) -> f64 {
+        let (start, end) = (*self.range.start(), *self.range.end());
+        if end > start {
+            (self.value - start) / (end - start)
+        } else {
+            0.0
+        }
+    }
+    /// Changes the value by `steps` steps, or hundredths of the range without a step
+    pub fn step_by(&mut self, steps: f64, ctx: &mut EventCtx) {
+        let (start, end) = (*self.range.start(), *self.range.end());
+        let step = self.step.unwrap_or((end - start) / 100.0);
+        self.change(self.value + steps * step, ctx);
+    }
+    fn change(&mut self, value: f64, ctx: &mut EventCtx) {
+        let value = self.snap(value);
+        if value == self.value {
+            return;
+        }
+        self.value = value;
+        ctx.request_redraw();
+        if let Some(on_change) = self.on_change.clone() {
+            ctx.defer(move || on_change(value));
+        }
+    }
+    fn drag_to(&mut self, x: f64, ctx: &mut EventCtx) {
+        let rect = ctx.rect;
+        let span = (rect.width as f64 - 2.0 * KNOB_RADIUS as f64).max(1.0);
+        let fraction = ((x - rect.x as f64 - KNOB_RADIUS as f64) / span).clamp(0.0, 1.0);
+        let (start, end) = (*self.range.start(), *self.range.end());
+        self.change(start + fraction * (end - start), ctx);
+    }
+}
+
+impl Widget for Slider {
+    fn measure(&mut self, _theme: &Theme) -> Size {
+        Size::new(120, 2 * KNOB_RADIUS as i32 + 4)
+    }
+    fn draw(&mut self, ctx: &mut DrawCtx) {
+        let (theme, state, rect) = (ctx.theme, ctx.state, ctx.rect);
+        let radius = KNOB_RADIUS as i32;
+        let track = Rect::new(
+            rect.x + radius,
+            rect.y + (rect.height - TRACK_HEIGHT) / 2,
+            (rect.width - 2 * radius).max(0),
+            TRACK_HEIGHT,
+        );
+        let filled = (track.width as f64 * self.fraction()).round() as i32;
+        ctx.canvas.fill_rect(track, theme.surface_pressed);
+        let fill = match state {
+            _ if state.disabled => theme.text_disabled,
+            _ if state.hovered || state.pressed => theme.accent_hover,
+            _ => theme.accent,
+        };
+        ctx.canvas.fill_rect(Rect { width: filled, ..track }, fill);
+        let cx = (track.x + filled) as f32;
+        let cy = track.y as f32 + TRACK_HEIGHT as f32 / 2.0;
+        ctx.canvas.fill_circle(cx, cy, KNOB_RADIUS, fill);
+        ctx.canvas.fill_circle(cx, cy, KNOB_RADIUS - 3.0, theme.on_accent);
+    }
+    fn focusable(&self) -> bool {
+        true
+    }
+    fn event(&mut self, evt: WidgetEvent, ctx: &mut EventCtx) {
+        match evt {
+            WidgetEvent::PointerButton { button: BTN_LEFT, pressed: true, x, .. } => self.drag_to(x, ctx),
+            WidgetEvent::PointerMotion { x, .. } if ctx.state.pressed => self.drag_to(x, ctx),
+            WidgetEvent::Key(key) if key.pressed => match key.keysym {
+                keysyms::XKB_KEY_Left | keysyms::XKB_KEY_Down => self.step_by(-1.0, ctx),
+                keysyms::XKB_KEY_Right | keysyms::XKB_KEY_Up => self.step_by(1.0, ctx),
+                keysyms::XKB_KEY_Page_Down => self.step_by(-10.0, ctx),
+                keysyms::XKB_KEY_Page_Up => self.step_by(10.0, ctx),
+                keysyms::XKB_KEY_Home => self.change(*self.range.start(), ctx),
+                keysyms::XKB_KEY_End => self.change(*self.range.end(), ctx),
+                _ => (),
+            },
+            _ => (),
+        }
+    }
+}
@@ -0,0 +1,975 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use cosmic_text::{AttrsList, Buffer, BufferLine, Cursor, LayoutCursor};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::data_device::{DataRead, Selection};
+use crate::get_globals;
+use crate::keyboard::{keysyms, KeyEvent};
+use crate::prelude::*;
+use crate::renderer::text_buffer;
+use crate::window::xdg_shell::{ContentHint, ContentPurpose, Preedit, TextInputClient};
+
+use super::{DrawCtx, EventCtx, TextCb, Theme, Widget, WidgetEvent, BTN_LEFT, BTN_MIDDLE};
+
+const PADDING: Insets = Insets::symmetric(6, 8);
+const DEFAULT_WIDTH: i32 = 200;
+/// Visible lines of a multi-line entry, before the layout grows it
+const DEFAULT_LINES: i32 = 4;
+const CURSOR_WIDTH: i32 = 1;
+/// Shown instead of each character of a password
+const MASK: char = '•';
+// Older edits are forgotten
+const MAX_UNDO: usize = 100;
+
+/// Consecutive edits of the same kind are undone together
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EditKind {
+    Insert,
+    Delete,
+    Other,
+}
+
+#[derive(Debug, Clone)]
+struct Snapshot {
+    text: String,
+    cursor: Cursor,
+}
+
+/// Where a key moves the cursor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Motion {
+    /// Previous grapheme, or the end of the line above at the start of a line
+    Previous,
+    Next,
+    /// Previous or next grapheme, whichever is on that side in the direction of the line
+    Left,
+    Right,
+    /// Start of the word before the cursor
+    PreviousWord,
+    /// End of the word after the cursor
+    NextWord,
+    Up,
+    Down,
+    /// Start and end of the visual line
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    BufferStart,
+    BufferEnd,
+}
+
+/// Orders cursors the way they appear in the text
+fn position(cursor: Cursor) -> (usize, usize) {
+    (cursor.line, cursor.index)
+}
+
+/// `a` and `b` in the order they appear in the text
+fn ordered(a: Cursor, b: Cursor) -> (Cursor, Cursor) {
+    match position(a) <= position(b) {
+        true => (a, b),
+        false => (b, a),
+    }
+}
+
+/// Where `cursor` is drawn in `buffer`: the x of its glyph and the top of its line
+fn caret_position(buffer: &Buffer, cursor: Cursor) -> Option<(i32, i32)> {
+    let metrics = buffer.metrics();
+    let mut found = None;
+    for run in buffer.layout_runs().filter(|run| run.line_i == cursor.line) {
+        let top = run.line_y - metrics.font_size;
+        // Wrapped lines have a run per visual line, the cursor is in the last one starting before it
+        if run.glyphs.first().is_some_and(|glyph| glyph.start > cursor.index) {
+            break;
+        }
+        found = Some((0, top));
+        for glyph in run.glyphs.iter() {
+            if glyph.start <= cursor.index && cursor.index < glyph.end {
+                return Some((glyph.x as i32, top));
+            }
+            found = Some(((glyph.x + glyph.w) as i32, top));
+        }
+    }
+    found
+}
+
+/// Text, cursor and history of a [`TextEntry`], shared with the input method. It edits the
+/// buffer itself, cosmic-text's `Editor` can't place the cursor or insert whole strings.
+struct EditState {
+    buffer: Buffer<'static>,
+    cursor: Cursor,
+    /// Where the selection started, it reaches from there to the cursor
+    anchor: Option<Cursor>,
+    /// Glyph Up and Down keep the cursor at, through lines too short for it, and where they
+    /// last left the cursor. Any other move forgets it.
+    goal: Option<(usize, Cursor)>,
+    /// Font of the theme the text was laid out with, it is fixed once the text is set
+    font_family: Option<String>,
+    multiline: bool,
+    password: bool,
+    placeholder: String,
+    preedit: Option<Preedit>,
+    undo: Vec<Snapshot>,
+    redo: Vec<Snapshot>,
+    last_edit: Option<EditKind>,
+    /// The text changed since the change handler was last called
+    changed: bool,
+    /// A left button drag is selecting text
+    selecting: bool,
+    /// Text being pasted, inserted once the other client is done writing it
+    paste: Option<DataRead>,
+    /// Horizontal scroll of a single line, in pixels
+    scroll_x: i32,
+    /// Where the start of the text was drawn last, in surface coordinates
+    text_origin: (i32, i32),
+    cursor_rect: Rect,
+}
+
+impl EditState {
+    fn new(text: &str) -> Self {
+        let mut this = Self {
+            buffer: text_buffer(Theme::default().metrics(), text),
+            cursor: Cursor::default(),
+            anchor: None,
+            goal: None,
+            font_family: None,
+            multiline: false,
+            password: false,
+            placeholder: String::new(),
+            preedit: None,
+            undo: Vec::new(),
+            redo: Vec::new(),
+            last_edit: None,
+            changed: false,
+            selecting: false,
+            paste: None,
+            scroll_x: 0,
+            text_origin: (0, 0),
+            cursor_rect: Rect::default(),
+        };
+        this.cursor = this.end();
+        this
+    }
+    fn text(&self) -> String {
+        let lines: Vec<&str> = self.buffer.lines.iter().map(|line| line.text()).collect();
+        lines.join("\n")
+    }
+    fn line(&self, line: usize) -> &str {
+        self.buffer.lines.get(line).map_or("", |line| line.text())
+    }
+    /// Right after the last character
+    fn end(&self) -> Cursor {
+        let last = self.buffer.lines.len().saturating_sub(1);
+        Cursor::new(last, self.line(last).len())
+    }
+    /// Byte offset of `cursor` in [`EditState::text`]
+    fn offset(&self, cursor: Cursor) -> usize {
+        let lines = &self.buffer.lines[..cursor.line];
+        lines.iter().map(|line| line.text().len() + 1).sum::<usize>() + cursor.index
+    }
+    fn cursor_at(&self, mut offset: usize) -> Cursor {
+        for (i, line) in self.buffer.lines.iter().enumerate() {
+            if offset <= line.text().len() {
+                return Cursor::new(i, offset);
+            }
+            offset -= line.text().len() + 1;
+        }
+        self.end()
+    }
+    fn set_cursor(&mut self, cursor: Cursor) {
+        self.cursor = cursor;
+        self.anchor = None;
+    }
+    /// Replaces the whole text, forgetting the history
+    fn set_text(&mut self, text: &str) {
+        self.replace_text(text);
+        self.set_cursor(self.end());
+        self.undo.clear();
+        self.redo.clear();
+        self.last_edit = None;
+        self.scroll_x = 0;
+    }
+    /// The cursor and selection are left as they are
+    fn replace_text(&mut self, text: &str) {
+        let (metrics, size) = (self.buffer.metrics(), self.buffer.size());
+        self.buffer = text_buffer(metrics, text);
+        self.buffer.set_size(size.0, size.1);
+    }
+    /// Replaces the text from `start` to `end` with `text`, returning the cursor right after it
+    fn replace(&mut self, start: Cursor, end: Cursor, text: &str) -> Cursor {
+        let first = &self.buffer.lines[start.line];
+        let after = &self.buffer.lines[end.line].text()[end.index..];
+        let joined = [&first.text()[..start.index], text, after].concat();
+        let last = joined.rsplit('\n').next().unwrap_or_default();
+        let cursor = Cursor::new(start.line + text.matches('\n').count(), last.len() - after.len());
+        let attrs = first.attrs_list().defaults();
+        let lines: Vec<BufferLine> = joined
+            .split('\n')
+            .map(|line| BufferLine::new(line, AttrsList::new(attrs)))
+            .collect();
+        self.buffer.lines.splice(start.line..=end.line, lines);
+        cursor
+    }
+    /// Start and end of the selection, `None` if nothing is selected
+    fn selection(&self) -> Option<(Cursor, Cursor)> {
+        let anchor = self.anchor.filter(|anchor| *anchor != self.cursor)?;
+        Some(ordered(anchor, self.cursor))
+    }
+    fn selected_text(&self) -> Option<String> {
+        let (start, end) = self.selection()?;
+        let (start, end) = (self.offset(start), self.offset(end));
+        Some(self.text()[start..end].to_owned())
+    }
+    /// Visual line and glyph of `cursor`, laying its line out first
+    fn layout_cursor(&mut self, cursor: Cursor) -> LayoutCursor {
+        self.buffer.line_layout(cursor.line);
+        self.buffer.layout_cursor(&cursor)
+    }
+    /// The cursor at `layout`, clamped to the glyphs of its visual line
+    fn layout_to_cursor(&mut self, layout: LayoutCursor) -> Cursor {
+        let visuals = self.buffer.line_layout(layout.line).unwrap_or_default();
+        let Some(visual) = visuals.get(layout.layout).or(visuals.last()) else {
+            return Cursor::new(layout.line, 0);
+        };
+        let index = match visual.glyphs.get(layout.glyph) {
+            Some(glyph) => glyph.start,
+            None => visual.glyphs.last().map_or(0, |glyph| glyph.end),
+        };
+        Cursor::new(layout.line, index)
+    }
+    /// Number of visual lines `line` is wrapped into
+    fn visual_lines(&mut self, line: usize) -> usize {
+        self.buffer.line_layout(line).map_or(1, |visuals| visuals.len().max(1))
+    }
+    /// The visual line above or below `layout`, or itself at either end of the text
+    fn step_layout(&mut self, layout: LayoutCursor, up: bool) -> LayoutCursor {
+        let LayoutCursor { line, layout: visual, glyph } = layout;
+        match up {
+            true if visual > 0 => LayoutCursor::new(line, visual - 1, glyph),
+            true if line > 0 => LayoutCursor::new(line - 1, self.visual_lines(line - 1) - 1, glyph),
+            false if visual + 1 < self.visual_lines(line) => LayoutCursor::new(line, visual + 1, glyph),
+            false if line + 1 < self.buffer.lines.len() => LayoutCursor::new(line + 1, 0, glyph),
+            _ => layout,
+        }
+    }
+    /// Where `motion` takes the cursor
+    fn target(&mut self, motion: Motion) -> Cursor {
+        let Cursor { line, index } = self.cursor;
+        let text = self.line(line);
+        match motion {
+            Motion::Previous => match text[..index].grapheme_indices(true).next_back() {
+                Some((start, _)) => Cursor::new(line, start),
+                None if line > 0 => Cursor::new(line - 1, self.line(line - 1).len()),
+                None => self.cursor,
+            },
+            Motion::Next => match text[index..].graphemes(true).next() {
+                Some(grapheme) => Cursor::new(line, index + grapheme.len()),
+                None if line + 1 < self.buffer.lines.len() => Cursor::new(line + 1, 0),
+                None => self.cursor,
+            },
+            Motion::Left | Motion::Right => {
+                let rtl = self.buffer.line_shape(line).is_some_and(|shape| shape.rtl);
+                match (motion == Motion::Right) != rtl {
+                    true => self.target(Motion::Next),
+                    false => self.target(Motion::Previous),
+                }
+            }
+            Motion::PreviousWord => match text[..index].unicode_word_indices().next_back() {
+                Some((start, _)) => Cursor::new(line, start),
+                None if index > 0 => Cursor::new(line, 0),
+                None => self.target(Motion::Previous),
+            },
+            Motion::NextWord => match text[index..].unicode_word_indices().next() {
+                Some((start, word)) => Cursor::new(line, index + start + word.len()),
+                None if index < text.len() => Cursor::new(line, text.len()),
+                None => self.target(Motion::Next),
+            },
+            Motion::Up | Motion::Down | Motion::PageUp | Motion::PageDown => {
+                let steps = match motion {
+                    Motion::Up | Motion::Down => 1,
+                    _ => self.buffer.visible_lines().max(1),
+                };
+                let up = matches!(motion, Motion::Up | Motion::PageUp);
+                let mut layout = self.layout_cursor(self.cursor);
+                let glyph = match self.goal {
+                    Some((glyph, at)) if at == self.cursor => glyph,
+                    _ => layout.glyph,
+                };
+                for _ in 0..steps {
+                    layout = self.step_layout(layout, up);
+                }
+                let target = self.layout_to_cursor(LayoutCursor { glyph, ..layout });
+                self.goal = Some((glyph, target));
+                target
+            }
+            Motion::Home | Motion::End => {
+                let layout = self.layout_cursor(self.cursor);
+                let glyph = if motion == Motion::Home { 0 } else { usize::MAX };
+                self.layout_to_cursor(LayoutCursor { glyph, ..layout })
+            }
+            Motion::BufferStart => Cursor::default(),
+            Motion::BufferEnd => self.end(),
+        }
+    }
+    /// Moves the cursor to `target`, extending the selection to it or dropping it
+    fn move_to(&mut self, target: Cursor, extend: bool) {
+        match extend {
+            true => {
+                self.anchor.get_or_insert(self.cursor);
+            }
+            false => self.anchor = None,
+        }
+        self.cursor = target;
+        self.last_edit = None;
+    }
+    fn move_cursor(&mut self, motion: Motion, extend: bool) {
+        let target = self.target(motion);
+        self.move_to(target, extend);
+    }
+    fn select_all(&mut self) {
+        self.anchor = Some(Cursor::default());
+        self.cursor = self.end();
+    }
+    /// Runs `edit`, remembering the text before for undo if it changed
+    fn edit(&mut self, kind: EditKind, edit: impl FnOnce(&mut Self)) {
+        let before = Snapshot {
+            text: self.text(),
+            cursor: self.cursor,
+        };
+        edit(self);
+        if self.text() == before.text {
+            return;
+        }
+        if kind == EditKind::Other || self.last_edit != Some(kind) {
+            if self.undo.len() == MAX_UNDO {
+                self.undo.remove(0);
+            }
+            self.undo.push(before);
+        }
+        self.redo.clear();
+        self.last_edit = Some(kind);
+        self.changed = true;
+    }
+    fn insert(&mut self, text: &str, kind: EditKind) {
+        let text = match self.multiline {
+            true => text.replace("\r\n", "\n"),
+            false => text.replace(['\r', '\n'], " "),
+        };
+        self.edit(kind, |state| {
+            let (start, end) = state.selection().unwrap_or((state.cursor, state.cursor));
+            let cursor = state.replace(start, end, &text);
+            state.set_cursor(cursor);
+        });
+    }
+    /// Deletes the selection, or from the cursor to where `motion` moves it
+    fn delete(&mut self, motion: Motion) {
+        self.edit(EditKind::Delete, |state| {
+            let (start, end) = match state.selection() {
+                Some(selection) => selection,
+                None => {
+                    let target = state.target(motion);
+                    ordered(state.cursor, target)
+                }
+            };
+            let cursor = state.replace(start, end, "");
+            state.set_cursor(cursor);
+        });
+    }
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.replace_text(&snapshot.text);
+        self.set_cursor(snapshot.cursor);
+        self.last_edit = None;
+        self.changed = true;
+    }
+    fn undo(&mut self) {
+        if let Some(snapshot) = self.undo.pop() {
+            self.redo.push(Snapshot {
+                text: self.text(),
+                cursor: self.cursor,
+            });
+            self.restore(snapshot);
+        }
+    }
+    fn redo(&mut self) {
+        if let Some(snapshot) = self.redo.pop() {
+            self.undo.push(Snapshot {
+                text: self.text(),
+                cursor: self.cursor,
+            });
+            self.restore(snapshot);
+        }
+    }
+    /// Where `cursor` ends up in the text that is shown, which is masked for passwords
+    fn display_cursor(&self, cursor: Cursor) -> Cursor {
+        if !self.password {
+            return cursor;
+        }
+        let chars = self.line(cursor.line)[..cursor.index].chars().count();
+        Cursor::new(cursor.line, chars * MASK.len_utf8())
+    }
+    /// What is shown when it differs from the text: masked, or with the preedit
+    fn display_text(&self) -> Option<String> {
+        if !self.password && self.preedit.is_none() {
+            return None;
+        }
+        let mut lines: Vec<String> = self
+            .buffer
+            .lines
+            .iter()
+            .map(|line| match self.password {
+                true => MASK.to_string().repeat(line.text().chars().count()),
+                false => line.text().to_owned(),
+            })
+            .collect();
+        if let Some(preedit) = &self.preedit {
+            let at = self.display_cursor(self.cursor);
+            lines[at.line].insert_str(at.index, &preedit.text);
+        }
+        Some(lines.join("\n"))
+    }
+    /// Turns a point in surface coordinates into one the buffer understands
+    fn buffer_point(&self, x: f64, y: f64) -> (i32, i32) {
+        let (x, y) = (x as i32 - self.text_origin.0, y as i32 - self.text_origin.1);
+        if !self.password {
+            return (x, y);
+        }
+        // Finds the character under the point in the masked text, then where it is
+        // in the real one
+        let metrics = self.buffer.metrics();
+        let mut masked = text_buffer(metrics, &self.display_text().unwrap_or_default());
+        masked.set_size(i32::MAX, metrics.line_height);
+        masked.shape_until_scroll();
+        let Some(hit) = masked.hit(x, y) else {
+            return (x, y);
+        };
+        let chars = hit.index / MASK.len_utf8();
+        let line = self.line(0);
+        let index = line.char_indices().nth(chars).map_or(line.len(), |(i, _)| i);
+        let real_x = caret_position(&self.buffer, Cursor::new(0, index)).map_or(x, |(x, _)| x);
+        (real_x, y)
+    }
+    fn click(&mut self, x: f64, y: f64, extend: bool) {
+        let (x, y) = self.buffer_point(x, y);
+        if let Some(hit) = self.buffer.hit(x, y) {
+            self.move_to(hit, extend);
+        }
+    }
+    fn drag(&mut self, x: f64, y: f64) {
+        self.click(x, y, true);
+    }
+    /// Offers the selection to middle click pastes. Passwords are never shared.
+    fn publish_primary(&self, serial: u32) {
+        if self.password {
+            return;
+        }
+        if let (Some(text), Some(primary)) = (self.selected_text(), get_globals().primary_selection()) {
+            primary.set_text(serial, text);
+        }
+    }
+    fn copy(&self, serial: u32) {
+        if self.password {
+            return;
+        }
+        if let (Some(text), Some(clipboard)) = (self.selected_text(), get_globals().clipboard()) {
+            clipboard.set_text(serial, text);
+        }
+    }
+    /// Copies and deletes the selection. Passwords are left as they are.
+    fn cut(&mut self, serial: u32) {
+        if self.password || self.selection().is_none() {
+            return;
+        }
+        self.copy(serial);
+        self.delete(Motion::Next);
+    }
+    /// Starts reading the text of `selection`, see [`EditState::poll_paste`]
+    fn paste(&mut self, selection: &impl Selection) {
+        if let Some(Ok(read)) = selection.read_text() {
+            self.paste = Some(read);
+        }
+    }
+    /// Inserts the pasted text once it was read. Returns `false` while the other client is
+    /// still writing it, waiting on it would freeze the whole event loop.
+    fn poll_paste(&mut self) -> bool {
+        let Some(read) = &self.paste else {
+            return true;
+        };
+        let Some(result) = read.try_recv() else {
+            return false;
+        };
+        self.paste = None;
+        if let Ok(data) = result {
+            self.insert(&String::from_utf8_lossy(&data), EditKind::Other);
+        }
+        true
+    }
+    fn draw(&mut self, ctx: &mut DrawCtx, inner: Rect) {
+        let (theme, state) = (ctx.theme, ctx.state);
+        let metrics = theme.metrics();
+        if self.font_family != theme.font_family {
+            self.font_family = theme.font_family.clone();
+            self.replace_text(&self.text());
+        }
+        let size = match self.multiline {
+            true => (inner.width, inner.height),
+            false => (i32::MAX, metrics.line_height),
+        };
+        if self.buffer.metrics() != metrics {
+            self.buffer.set_metrics(metrics);
+        }
+        if self.buffer.size() != size {
+            self.buffer.set_size(size.0, size.1);
+        }
+        // Also scrolls the cursor's line into view
+        self.buffer.shape_until_cursor(self.cursor);
+        let top = match self.multiline {
+            true => inner.y,
+            false => inner.y + (inner.height - metrics.line_height) / 2,
+        };
+
+        let display = self.display_text().map(|text| {
+            let mut buffer = text_buffer(metrics, &text);
+            buffer.set_size(size.0, size.1);
+            buffer.set_scroll(self.buffer.scroll());
+            buffer.shape_until_scroll();
+            buffer
+        });
+        let buffer = display.as_ref().unwrap_or(&self.buffer);
+        let cursor = self.display_cursor(self.cursor);
+        let (caret, selection) = match &self.preedit {
+            Some(preedit) => {
+                let caret = preedit.cursor.map(|(begin, _)| Cursor::new(cursor.line, cursor.index + begin));
+                (caret, None)
+            }
+            None => {
+                let selection = self
+                    .selection()
+                    .map(|(start, end)| (self.display_cursor(start), self.display_cursor(end)));
+                (Some(cursor), selection)
+            }
+        };
+        let caret_pos = caret.and_then(|caret| caret_position(buffer, caret));
+
+        // Keeps the cursor of a single line in view
+        if !self.multiline {
+            let text_width = buffer
+                .layout_runs()
+                .flat_map(|run| run.glyphs.last().map(|glyph| (glyph.x + glyph.w) as i32))
+                .max()
+                .unwrap_or(0);
+            if let Some((x, _)) = caret_pos {
+                if x - self.scroll_x > inner.width - CURSOR_WIDTH {
+                    self.scroll_x = x - inner.width + CURSOR_WIDTH;
+                }
+                self.scroll_x = self.scroll_x.min(x);
+            }
+            self.scroll_x = self.scroll_x.min(text_width + CURSOR_WIDTH - inner.width).max(0);
+        }
+        let origin = (inner.x - self.scroll_x, top);
+        self.text_origin = origin;
+
+        if let Some((start, end)) = selection {
+            for run in buffer.layout_runs() {
+                if run.line_i < start.line || run.line_i > end.line {
+                    continue;
+                }
+                let from = if run.line_i == start.line { start.index } else { 0 };
+                let to = if run.line_i == end.line { end.index } else { usize::MAX };
+                for glyph in run.glyphs.iter().filter(|glyph| glyph.start >= from && glyph.end <= to) {
+                    let rect = Rect::new(
+                        origin.0 + glyph.x as i32,
+                        origin.1 + run.line_y - metrics.font_size,
+                        glyph.w.ceil() as i32,
+                        metrics.line_height,
+                    );
+                    ctx.canvas.fill_rect(rect.intersect(inner), theme.selection);
+                }
+            }
+        }
+
+        if buffer.lines.iter().all(|line| line.text().is_empty()) {
+            let rect = Rect::new(inner.x, top, inner.width, metrics.line_height);
+            ctx.text(rect, &self.placeholder, theme.text_disabled);
+        } else {
+            let color = theme.text_color(state.disabled);
+            ctx.text.draw_buffer_clipped(ctx.canvas, buffer, origin, inner, color);
+        }
+        if let Some(preedit) = &self.preedit {
+            let range = cursor.index..cursor.index + preedit.text.len();
+            ctx.text.draw_underline(ctx.canvas, buffer, origin, cursor.line, range, theme.text);
+        }
+
+        let (x, y) = caret_pos.unwrap_or((0, 0));
+        self.cursor_rect = Rect::new(origin.0 + x, origin.1 + y, CURSOR_WIDTH, metrics.line_height);
+        if state.focused && !state.disabled && caret_pos.is_some() {
+            ctx.canvas.fill_rect(self.cursor_rect.intersect(inner), theme.text);
+        }
+    }
+}
+
+impl TextInputClient for EditState {
+    fn surrounding_text(&self) -> (String, usize, usize) {
+        // Input methods may remember what they see
+        if self.password {
+            return (String::new(), 0, 0);
+        }
+        let cursor = self.offset(self.cursor);
+        let anchor = self.anchor.map_or(cursor, |anchor| self.offset(anchor));
+        (self.text(), cursor, anchor)
+    }
+    fn cursor_rect(&self) -> Rect {
+        self.cursor_rect
+    }
+    fn content_type(&self) -> (ContentHint, ContentPurpose) {
+        match (self.password, self.multiline) {
+            (true, _) => (ContentHint::HiddenText | ContentHint::SensitiveData, ContentPurpose::Password),
+            (false, true) => (ContentHint::Multiline, ContentPurpose::Normal),
+            (false, false) => (ContentHint::None, ContentPurpose::Normal),
+        }
+    }
+    fn set_preedit(&mut self, preedit: Option<Preedit>) {
+        self.preedit = preedit.filter(|preedit| !preedit.text.is_empty());
+    }
+    fn commit_string(&mut self, text: &str) {
+        self.insert(text, EditKind::Insert);
+    }
+    fn delete_surrounding_text(&mut self, before: usize, after: usize) {
+        let cursor = self.offset(self.cursor);
+        let text = self.text();
+        let (start, end) = (cursor.saturating_sub(before), (cursor + after).min(text.len()));
+        if !text.is_char_boundary(start) || !text.is_char_boundary(end) {
+            return;
+        }
+        let (start, end) = (self.cursor_at(start), self.cursor_at(end));
+        self.edit(EditKind::Delete, |state| {
+            let cursor = state.replace(start, end, "");
+            state.set_cursor(cursor);
+        });
+    }
+}
+
+/// Editable text, a single line unless made multi-line. It takes the input method text
+/// while focused, and shares its selection with middle click pastes.
+pub struct TextEntry {
+    state: Rc<RefCell<EditState>>,
+    on_change: Option<Rc<dyn TextCb>>,
+    on_activate: Option<Rc<dyn TextCb>>,
+}
+
+impl std::fmt::Debug for TextEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = self.state.borrow();
+        f.debug_struct(std::any::type_name::<Self>())
+            .field("multiline", &state.multiline)
+            .field("password", &state.password)
+            .field("placeholder", &state.placeholder)
+            .field("preedit", &state.preedit)
+            .field("on_change", &"Cool closure")
+            .field("on_activate", &"Cool closure")
+            .finish()
+    }
+}
+
+impl TextEntry {
+    pub fn new(text: &str) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(EditState::new(text))),
+            on_change: None,
+            on_activate: None,
+        }
+    }
+    /// Enter inserts line breaks instead of activating the entry
+    pub fn multiline(self, multiline: bool) -> Self {
+        self.state.borrow_mut().multiline = multiline;
+        self
+    }
+    /// Shown while the entry is empty
+    pub fn placeholder(self, placeholder: impl Into<String>) -> Self {
+        self.state.borrow_mut().placeholder = placeholder.into();
+        self
+    }
+    /// Masks the text, and keeps it out of the clipboard and the input method.
+    /// Meant for single-line entries.
+    pub fn password(self, password: bool) -> Self {
+        self.state.borrow_mut().password = password;
+        self
+    }
+    /// Called with the new text after every edit
+    pub fn on_change(mut self, on_change: impl TextCb + 'static) -> Self {
+        self.on_change = Some(Rc::new(on_change));
+        self
+    }
+    /// Called with the text when Enter is pressed in a single-line entry
+    pub fn on_activate(mut self, on_activate: impl TextCb + 'static) -> Self {
+        self.on_activate = Some(Rc::new(on_activate));
+        self
+    }
+    pub fn text(&self) -> String {
+        self.state.borrow().text()
+    }
+    /// Doesn't call the change handler, and clears the undo history
+    pub fn set_text(&mut self, text: &str) {
+        self.state.borrow_mut().set_text(text);
+    }
+    pub fn selected_text(&self) -> Option<String> {
+        self.state.borrow().selected_text()
+    }
+    /// Calls the change handler if the text changed since last time
+    fn notify_change(&self, ctx: &mut EventCtx) {
+        let mut state = self.state.borrow_mut();
+        if !std::mem::take(&mut state.changed) {
+            return;
+        }
+        if let Some(on_change) = self.on_change.clone() {
+            let text = state.text();
+            ctx.defer(move || on_change(&text));
+        }
+    }
+    /// Returns `false` if the key isn't used by the entry
+    fn key(&self, key: KeyEvent, ctx: &mut EventCtx) -> bool {
+        let mut state = self.state.borrow_mut();
+        let (ctrl, shift) = (key.modifiers.ctrl, key.modifiers.shift);
+        let motion = match key.keysym {
+            keysyms::XKB_KEY_Left | keysyms::XKB_KEY_KP_Left if ctrl => Some(Motion::PreviousWord),
+            keysyms::XKB_KEY_Left | keysyms::XKB_KEY_KP_Left => Some(Motion::Left),
+            keysyms::XKB_KEY_Right | keysyms::XKB_KEY_KP_Right if ctrl => Some(Motion::NextWord),
+            keysyms::XKB_KEY_Right | keysyms::XKB_KEY_KP_Right => Some(Motion::Right),
+            keysyms::XKB_KEY_Up | keysyms::XKB_KEY_KP_Up => Some(Motion::Up),
+            keysyms::XKB_KEY_Down | keysyms::XKB_KEY_KP_Down => Some(Motion::Down),
+            keysyms::XKB_KEY_Home | keysyms::XKB_KEY_KP_Home if ctrl => Some(Motion::BufferStart),
+            keysyms::XKB_KEY_Home | keysyms::XKB_KEY_KP_Home => Some(Motion::Home),
+            keysyms::XKB_KEY_End | keysyms::XKB_KEY_KP_End if ctrl => Some(Motion::BufferEnd),
+            keysyms::XKB_KEY_End | keysyms::XKB_KEY_KP_End => Some(Motion::End),
+            keysyms::XKB_KEY_Page_Up => Some(Motion::PageUp),
+            keysyms::XKB_KEY_Page_Down => Some(Motion::PageDown),
+            _ => None,
+        };
+        if let Some(motion) = motion {
+            state.move_cursor(motion, shift);
+            if shift {
+                state.publish_primary(key.serial);
+            }
+            return true;
+        }
+        match key.keysym {
+            keysyms::XKB_KEY_BackSpace if ctrl => state.delete(Motion::PreviousWord),
+            keysyms::XKB_KEY_BackSpace => state.delete(Motion::Previous),
+            keysyms::XKB_KEY_Delete | keysyms::XKB_KEY_KP_Delete if shift => state.cut(key.serial),
+            keysyms::XKB_KEY_Delete | keysyms::XKB_KEY_KP_Delete if ctrl => state.delete(Motion::NextWord),
+            keysyms::XKB_KEY_Delete | keysyms::XKB_KEY_KP_Delete => state.delete(Motion::Next),
+            keysyms::XKB_KEY_Return | keysyms::XKB_KEY_KP_Enter if state.multiline => {
+                state.insert("\n", EditKind::Other)
+            }
+            keysyms::XKB_KEY_Return | keysyms::XKB_KEY_KP_Enter => {
+                if let Some(on_activate) = self.on_activate.clone() {
+                    let text = state.text();
+                    ctx.defer(move || on_activate(&text));
+                }
+            }
+            keysyms::XKB_KEY_Escape if state.selection().is_some() => state.anchor = None,
+            keysyms::XKB_KEY_Insert if ctrl => state.copy(key.serial),
+            keysyms::XKB_KEY_Insert if shift => {
+                if let Some(clipboard) = get_globals().clipboard() {
+                    state.paste(&clipboard);
+                }
+            }
+            keysyms::XKB_KEY_a | keysyms::XKB_KEY_A if ctrl => {
+                state.select_all();
+                state.publish_primary(key.serial);
+            }
+            keysyms::XKB_KEY_c | keysyms::XKB_KEY_C if ctrl => state.copy(key.serial),
+            keysyms::XKB_KEY_x | keysyms::XKB_KEY_X if ctrl => state.cut(key.serial),
+            keysyms::XKB_KEY_v | keysyms::XKB_KEY_V if ctrl => {
+                if let Some(clipboard) = get_globals().clipboard() {
+                    state.paste(&clipboard);
+                }
+            }
+            keysyms::XKB_KEY_z | keysyms::XKB_KEY_Z if ctrl && shift => state.redo(),
+            keysyms::XKB_KEY_z | keysyms::XKB_KEY_Z if ctrl => state.undo(),
+            keysyms::XKB_KEY_y | keysyms::XKB_KEY_Y if ctrl => state.redo(),
+            _ if ctrl || key.modifiers.alt || key.modifiers.logo => return false,
+            _ => match key.utf8.filter(|text| !text.is_empty() && !text.chars().any(char::is_control)) {
+                Some(text) => state.insert(&text, EditKind::Insert),
+                None => return false,
+            },
+        }
+        true
+    }
+}
+
+impl Widget for TextEntry {
+    fn measure(&mut self, theme: &Theme) -> Size {
+        let lines = if self.state.borrow().multiline { DEFAULT_LINES } else { 1 };
+        Size::new(DEFAULT_WIDTH, lines * theme.line_height + PADDING.vertical())
+    }
+    fn draw(&mut self, ctx: &mut DrawCtx) {
+        let (theme, state) = (ctx.theme, ctx.state);
+        ctx.canvas.fill_rect(ctx.rect, theme.field);
+        let border = if state.focused { theme.accent } else { theme.border };
+        ctx.canvas.stroke_rect(ctx.rect, 1, border);
+        let inner = ctx.rect.inset(PADDING);
+        self.state.borrow_mut().draw(ctx, inner);
+    }
+    fn focusable(&self) -> bool {
+        true
+    }
+    fn event(&mut self, evt: WidgetEvent, ctx: &mut EventCtx) {
+        match evt {
+            WidgetEvent::PointerButton { button: BTN_LEFT, pressed: true, x, y, .. } => {
+                let extend = get_globals().keyboard.modifiers().shift;
+                let mut state = self.state.borrow_mut();
+                state.click(x, y, extend);
+                state.selecting = true;
+            }
+            WidgetEvent::PointerMotion { x, y } if self.state.borrow().selecting => {
+                self.state.borrow_mut().drag(x, y);
+            }
+            WidgetEvent::PointerButton { button: BTN_LEFT, pressed: false, serial, .. } => {
+                let mut state = self.state.borrow_mut();
+                state.selecting = false;
+                state.publish_primary(serial);
+            }
+            WidgetEvent::PointerButton { button: BTN_MIDDLE, pressed: true, x, y, .. } => {
+                let mut state = self.state.borrow_mut();
+                state.click(x, y, false);
+                if let Some(primary) = get_globals().primary_selection() {
+                    state.paste(&primary);
+                }
+            }
+            WidgetEvent::Key(key) if key.pressed => {
+                if !self.key(key, ctx) {
+                    return;
+                }
+            }
+            WidgetEvent::AnimationFrame(_) => {
+                if !self.state.borrow_mut().poll_paste() {
+                    ctx.request_animation_frame();
+                    return;
+                }
+            }
+            WidgetEvent::InputMethod | WidgetEvent::FocusIn => (),
+            WidgetEvent::FocusOut => {
+                // A paste still being read would end up in an entry the user left
+                let mut state = self.state.borrow_mut();
+                state.selecting = false;
+                state.paste = None;
+            }
+            _ => return,
+        }
+        if self.state.borrow().paste.is_some() {
+            // Checked on every frame until it arrives
+            ctx.request_animation_frame();
+        }
+        self.notify_change(ctx);
+        ctx.request_redraw();
+    }
+    fn text_input_client(&self) -> Option<Rc<RefCell<dyn TextInputClient>>> {
+        Some(self.state.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Multi-line state wide enough not to wrap
+    fn multiline(text: &str) -> EditState {
+        let mut state = EditState::new(text);
+        state.multiline = true;
+        state.buffer.set_size(1000, 1000);
+        state
+    }
+
+    #[test]
+    fn replace_within_and_across_lines() {
+        let mut state = multiline("hello world");
+        assert_eq!(state.replace(Cursor::new(0, 0), Cursor::new(0, 5), "bye"), Cursor::new(0, 3));
+        assert_eq!(state.text(), "bye world");
+
+        let mut state = multiline("ab\ncd\nef");
+        assert_eq!(state.replace(Cursor::new(0, 1), Cursor::new(2, 1), "X\nY"), Cursor::new(1, 1));
+        assert_eq!(state.text(), "aX\nYf");
+        assert_eq!(state.buffer.lines.len(), 2);
+
+        let end = state.end();
+        assert_eq!(state.replace(end, end, "\n"), Cursor::new(2, 0));
+        assert_eq!(state.text(), "aX\nYf\n");
+    }
+
+    #[test]
+    fn offset_round_trip() {
+        let text = "héllo\nwörld\n\nx";
+        let state = multiline(text);
+        for (offset, _) in text.char_indices().chain([(text.len(), ' ')]) {
+            assert_eq!(state.offset(state.cursor_at(offset)), offset);
+        }
+        assert_eq!(state.cursor_at(7), Cursor::new(1, 0));
+        assert_eq!(state.cursor_at(text.len() + 10), state.end());
+    }
+
+    #[test]
+    fn undo_groups_edits_of_a_kind() {
+        let mut state = multiline("");
+        state.commit_string("a");
+        state.commit_string("b");
+        state.delete(Motion::Previous);
+        state.insert("cd", EditKind::Other);
+        state.insert("ef", EditKind::Other);
+        assert_eq!(state.text(), "acdef");
+        for text in ["acd", "a", "ab", ""] {
+            state.undo();
+            assert_eq!(state.text(), text);
+        }
+        state.redo();
+        assert_eq!(state.text(), "ab");
+    }
+
+    #[test]
+    fn moving_the_cursor_ends_a_group() {
+        let mut state = multiline("");
+        state.commit_string("a");
+        state.move_cursor(Motion::Previous, false);
+        state.commit_string("b");
+        assert_eq!(state.text(), "ba");
+        state.undo();
+        assert_eq!(state.text(), "a");
+    }
+
+    #[test]
+    fn delete_surrounding_text_on_char_boundaries() {
+        let mut state = multiline("aé");
+        // Would end in the middle of é
+        state.delete_surrounding_text(1, 0);
+        assert_eq!(state.text(), "aé");
+        state.delete_surrounding_text(2, 0);
+        assert_eq!(state.text(), "a");
+
+        let mut state = multiline("éa");
+        state.set_cursor(Cursor::default());
+        state.delete_surrounding_text(0, 1);
+        assert_eq!(state.text(), "éa");
+        state.delete_surrounding_text(0, 2);
+        assert_eq!(state.text(), "a");
+        assert_eq!(state.cursor, Cursor::default());
+    }
+
+    #[test]
+    fn up_and_down_keep_the_goal_column() {
+        let mut state = multiline("abcdef\nab\nabcdef");
+        state.set_cursor(Cursor::new(0, 4));
+        let mut moves = Vec::new();
+        for motion in [Motion::Down, Motion::Down, Motion::Up, Motion::Up] {
+            state.move_cursor(motion, false);
+            moves.push(state.cursor);
+        }
+        assert_eq!(moves, [Cursor::new(1, 2), Cursor::new(2, 4), Cursor::new(1, 2), Cursor::new(0, 4)]);
+
+        // Other moves forget it
+        state.move_cursor(Motion::Down, false);
+        state.move_cursor(Motion::Previous, false);
+        state.move_cursor(Motion::Down, false);
+        assert_eq!(state.cursor, Cursor::new(2, 1));
+    }
+}
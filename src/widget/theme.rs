@@ -0,0 +1,358 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context};
+use cosmic_text::Metrics;
+use rgb::RGBA8;
+use toml::value::Table;
+use toml::Value;
+
+/// Shadow cast by the window on what is below it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shadow {
+    pub color: RGBA8,
+    /// How far it fades out
+    pub radius: i32,
+    pub offset: (i32, i32),
+}
+
+/// Colors, fonts and sizes the widgets and the window frame are drawn with
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub background: RGBA8,
+    /// Face of buttons and tracks of sliders
+    pub surface: RGBA8,
+    pub surface_hover: RGBA8,
+    pub surface_pressed: RGBA8,
+    pub border: RGBA8,
+    /// Background of text entries
+    pub field: RGBA8,
+    /// Behind selected text
+    pub selection: RGBA8,
+    pub text: RGBA8,
+    pub text_disabled: RGBA8,
+    pub accent: RGBA8,
+    pub accent_hover: RGBA8,
+    /// Drawn on top of `accent`
+    pub on_accent: RGBA8,
+    pub focus_ring: RGBA8,
+    /// Resize borders around the window
+    pub frame: RGBA8,
+    /// `None` uses the default sans-serif font
+    pub font_family: Option<String>,
+    pub font_size: i32,
+    pub line_height: i32,
+    /// Width of the resize borders around the window
+    pub border_width: i32,
+    pub corner_radius: i32,
+    pub shadow: Shadow,
+    pub title_bar_height: i32,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::light()
+    }
+}
+
+impl Theme {
+    pub fn light() -> Self {
+        Self {
+            background: RGBA8::new(0xf0, 0xf0, 0xf0, 0xff),
+            surface: RGBA8::new(0xe0, 0xe0, 0xe0, 0xff),
+            surface_hover: RGBA8::new(0xd4, 0xd4, 0xd4, 0xff),
+            surface_pressed: RGBA8::new(0xc0, 0xc0, 0xc0, 0xff),
+            border: RGBA8::new(0xa0, 0xa0, 0xa0, 0xff),
+            field: RGBA8::new(0xff, 0xff, 0xff, 0xff),
+            selection: RGBA8::new(0x35, 0x84, 0xe4, 0x60),
+            text: RGBA8::new(0x20, 0x20, 0x20, 0xff),
+            text_disabled: RGBA8::new(0x90, 0x90, 0x90, 0xff),
+            accent: RGBA8::new(0x35, 0x84, 0xe4, 0xff),
+            accent_hover: RGBA8::new(0x4a, 0x93, 0xeb, 0xff),
+            on_accent: RGBA8::new(0xff, 0xff, 0xff, 0xff),
+            focus_ring: RGBA8::new(0x35, 0x84, 0xe4, 0xa0),
+            frame: RGBA8::new(0xff, 0xff, 0xff, 0xff),
+            font_family: None,
+            font_size: 14,
+            line_height: 20,
+            border_width: 20,
+            corner_radius: 8,
+            shadow: Shadow {
+                color: RGBA8::new(0, 0, 0, 0x40),
+                radius: 12,
+                offset: (0, 2),
+            },
+            title_bar_height: 32,
+        }
+    }
+    pub fn dark() -> Self {
+        Self {
+            background: RGBA8::new(0x24, 0x24, 0x24, 0xff),
+            surface: RGBA8::new(0x3a, 0x3a, 0x3a, 0xff),
+            surface_hover: RGBA8::new(0x45, 0x45, 0x45, 0xff),
+            surface_pressed: RGBA8::new(0x50, 0x50, 0x50, 0xff),
+            border: RGBA8::new(0x5a, 0x5a, 0x5a, 0xff),
+            field: RGBA8::new(0x1e, 0x1e, 0x1e, 0xff),
+            selection: RGBA8::new(0x35, 0x84, 0xe4, 0x80),
+            text: RGBA8::new(0xee, 0xee, 0xee, 0xff),
+            text_disabled: RGBA8::new(0x7a, 0x7a, 0x7a, 0xff),
+            frame: RGBA8::new(0x30, 0x30, 0x30, 0xff),
+            shadow: Shadow {
+                color: RGBA8::new(0, 0, 0, 0x80),
+                ..Self::light().shadow
+            },
+            ..Self::light()
+        }
+    }
+    pub fn metrics(&self) -> Metrics {
+        Metrics::new(self.font_size, self.line_height)
+    }
+    pub fn text_color(&self, disabled: bool) -> RGBA8 {
+        if disabled {
+            self.text_disabled
+        } else {
+            self.text
+        }
+    }
+    /// Overrides the fields set in a `[light]` or `[dark]` table of the config file
+    fn apply(&mut self, table: &Table) -> anyhow::Result<()> {
+        for (key, value) in table {
+            let context = || format!("Invalid value for `{key}`");
+            match key.as_str() {
+                "background" => self.background = parse_color(value).with_context(context)?,
+                "surface" => self.surface = parse_color(value).with_context(context)?,
+                "surface_hover" => self.surface_hover = parse_color(value).with_context(context)?,
+                "surface_pressed" => self.surface_pressed = parse_color(value).with_context(context)?,
+                "border" => self.border = parse_color(value).with_context(context)?,
+                "field" => self.field = parse_color(value).with_context(context)?,
+                "selection" => self.selection = parse_color(value).with_context(context)?,
+                "text" => self.text = parse_color(value).with_context(context)?,
+                "text_disabled" => self.text_disabled = parse_color(value).with_context(context)?,
+                "accent" => self.accent = parse_color(value).with_context(context)?,
+                "accent_hover" => self.accent_hover = parse_color(value).with_context(context)?,
+                "on_accent" => self.on_accent = parse_color(value).with_context(context)?,
+                "focus_ring" => self.focus_ring = parse_color(value).with_context(context)?,
+                "frame" => self.frame = parse_color(value).with_context(context)?,
+                "font_family" => {
+                    let family = value
+                        .as_str()
+                        .ok_or_else(|| anyhow!("Expected a string"))
+                        .with_context(context)?;
+                    self.font_family = Some(family.to_owned());
+                }
+                "font_size" => self.font_size = parse_size(value).with_context(context)?,
+                "line_height" => self.line_height = parse_size(value).with_context(context)?,
+                "border_width" => self.border_width = parse_size(value).with_context(context)?,
+                "corner_radius" => self.corner_radius = parse_size(value).with_context(context)?,
+                "title_bar_height" => self.title_bar_height = parse_size(value).with_context(context)?,
+                "shadow" => {
+                    let shadow = value
+                        .as_table()
+                        .ok_or_else(|| anyhow!("Expected a table"))
+                        .with_context(context)?;
+                    self.shadow.apply(shadow).context("In [shadow]")?;
+                }
+                _ => bail!("Unknown key `{key}`"),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Shadow {
+    fn apply(&mut self, table: &Table) -> anyhow::Result<()> {
+        for (key, value) in table {
+            let context = || format!("Invalid value for `{key}`");
+            match key.as_str() {
+                "color" => self.color = parse_color(value).with_context(context)?,
+                "radius" => self.radius = parse_size(value).with_context(context)?,
+                "offset" => {
+                    let offset = match value.as_array().map(Vec::as_slice) {
+                        Some([x, y]) => x.as_integer().zip(y.as_integer()),
+                        _ => None,
+                    };
+                    let (x, y) = offset.ok_or_else(|| anyhow!("Expected [x, y]")).with_context(context)?;
+                    self.offset = (x.try_into()?, y.try_into()?);
+                }
+                _ => bail!("Unknown key `{key}`"),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `"#rrggbb"` or `"#rrggbbaa"`
+fn parse_color(value: &Value) -> anyhow::Result<RGBA8> {
+    let text = value.as_str().ok_or_else(|| anyhow!("Expected a color string"))?;
+    let hex = text
+        .strip_prefix('#')
+        .filter(|hex| matches!(hex.len(), 6 | 8) && hex.is_ascii())
+        .ok_or_else(|| anyhow!("Expected #rrggbb or #rrggbbaa, got {text:?}"))?;
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16);
+    let alpha = if hex.len() == 8 { channel(6)? } else { 0xff };
+    Ok(RGBA8::new(channel(0)?, channel(2)?, channel(4)?, alpha))
+}
+
+fn parse_size(value: &Value) -> anyhow::Result<i32> {
+    let size = value.as_integer().ok_or_else(|| anyhow!("Expected an integer"))?;
+    if size < 0 {
+        bail!("Expected a positive size, got {size}");
+    }
+    Ok(size.try_into()?)
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ThemeVariant {
+    #[default]
+    Light,
+    Dark,
+}
+
+/// Both variants of the theme and the one in use, read from
+/// `$XDG_CONFIG_HOME/wlr-desktop/theme.toml`:
+///
+/// ```toml
+/// variant = "dark"
+/// reduce_motion = false
+/// icon_theme = "Adwaita"
+///
+/// [dark]
+/// accent = "#e66100"
+/// font_family = "Cantarell"
+///
+/// [dark.shadow]
+/// color = "#00000060"
+/// offset = [0, 4]
+/// ```
+///
+/// Each table only overrides what it sets of the built-in [`Theme::light`] and [`Theme::dark`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThemeConfig {
+    pub variant: ThemeVariant,
+    /// Animations jump to their end
+    pub reduce_motion: bool,
+    /// Name of the freedesktop icon theme icons are looked up in
+    pub icon_theme: String,
+    pub light: Theme,
+    pub dark: Theme,
+}
+
+impl Default for ThemeConfig {
+    fn default() -> Self {
+        Self {
+            variant: ThemeVariant::default(),
+            reduce_motion: false,
+            icon_theme: "hicolor".to_owned(),
+            light: Theme::light(),
+            dark: Theme::dark(),
+        }
+    }
+}
+
+impl ThemeConfig {
+    pub fn current(&self) -> &Theme {
+        match self.variant {
+            ThemeVariant::Light => &self.light,
+            ThemeVariant::Dark => &self.dark,
+        }
+    }
+    /// Where the config file is, `None` if neither `$XDG_CONFIG_HOME` nor `$HOME` are set
+    pub fn path() -> Option<PathBuf> {
+        let config_home = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| Some(PathBuf::from(std::env::var_os("HOME")?).join(".config")))?;
+        Some(config_home.join("wlr-desktop").join("theme.toml"))
+    }
+    /// Reads the config file. Without one, the built-in themes are used; a broken one is
+    /// reported and ignored.
+    pub fn load() -> Self {
+        let Some(path) = Self::path().filter(|path| path.exists()) else {
+            return Self::default();
+        };
+        Self::load_from(&path).unwrap_or_else(|e| {
+            eprintln!("Failed to load the theme from {}: {e:#}", path.display());
+            Self::default()
+        })
+    }
+    pub fn load_from(path: &Path) -> anyhow::Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let value: Value = text.parse()?;
+        let table = value.as_table().ok_or_else(|| anyhow!("Expected a table"))?;
+        let mut config = Self::default();
+        for (key, value) in table {
+            match key.as_str() {
+                "variant" => {
+                    config.variant = match value.as_str() {
+                        Some("light") => ThemeVariant::Light,
+                        Some("dark") => ThemeVariant::Dark,
+                        _ => bail!("`variant` should be \"light\" or \"dark\""),
+                    }
+                }
+                "reduce_motion" => {
+                    config.reduce_motion = value
+                        .as_bool()
+                        .ok_or_else(|| anyhow!("`reduce_motion` should be true or false"))?;
+                }
+                "icon_theme" => {
+                    let name = value.as_str().ok_or_else(|| anyhow!("`icon_theme` should be a string"))?;
+                    config.icon_theme = name.to_owned();
+                }
+                "light" | "dark" => {
+                    let theme = if key == "light" { &mut config.light } else { &mut config.dark };
+                    let overrides = value.as_table().ok_or_else(|| anyhow!("`{key}` should be a table"))?;
+                    theme.apply(overrides).with_context(|| format!("In [{key}]"))?;
+                }
+                _ => bail!("Unknown key `{key}`"),
+            }
+        }
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn variants_only_override_what_they_set() {
+        let config = ThemeConfig::parse(
+            "variant = \"dark\"\nicon_theme = \"Adwaita\"\n\
+             [light]\nfont_size = 16\n\
+             [dark]\nborder_width = 10\ntitle_bar_height = 40\n\
+             [dark.shadow]\noffset = [0, 4]\n",
+        )
+        .unwrap();
+        assert_eq!(config.variant, ThemeVariant::Dark);
+        assert_eq!(config.icon_theme, "Adwaita");
+        assert_eq!(config.light, Theme { font_size: 16, ..Theme::light() });
+        let shadow = Shadow { offset: (0, 4), ..Theme::dark().shadow };
+        assert_eq!(config.dark, Theme { border_width: 10, title_bar_height: 40, shadow, ..Theme::dark() });
+        assert_eq!(config.current(), &config.dark);
+    }
+
+    #[test]
+    fn colors_take_an_optional_alpha() {
+        let config = ThemeConfig::parse("[light]\naccent = \"#e66100\"\nselection = \"#3584e440\"\n").unwrap();
+        assert_eq!(config.light.accent, RGBA8::new(0xe6, 0x61, 0x00, 0xff));
+        assert_eq!(config.light.selection, RGBA8::new(0x35, 0x84, 0xe4, 0x40));
+        assert!(ThemeConfig::parse("[light]\naccent = \"#e661\"\n").is_err());
+        assert!(ThemeConfig::parse("[light]\naccent = \"e66100\"\n").is_err());
+    }
+
+    #[test]
+    fn unknown_keys_are_errors() {
+        assert!(ThemeConfig::parse("colour = \"dark\"\n").is_err());
+        assert!(ThemeConfig::parse("[light]\naccnet = \"#e66100\"\n").is_err());
+        assert!(ThemeConfig::parse("[dark.shadow]\nblur = 4\n").is_err());
+    }
+
+    #[test]
+    fn negative_sizes_are_errors() {
+        assert!(ThemeConfig::parse("[light]\nfont_size = -1\n").is_err());
+        assert!(ThemeConfig::parse("[dark]\ntitle_bar_height = -32\n").is_err());
+        assert!(ThemeConfig::parse("[dark.shadow]\nradius = -4\n").is_err());
+        assert!(ThemeConfig::parse("[light]\ncorner_radius = 0\n").is_ok());
+    }
+}
@@ -0,0 +1,90 @@
+use std::rc::Rc;
+
+use crate::keyboard::keysyms;
+use crate::prelude::*;
+
+use super::animation::Animatable;
+use super::{DrawCtx, EventCtx, Theme, ToggleCb, Widget, WidgetEvent, BTN_LEFT};
+
+const TRACK: Size = Size::new(36, 20);
+const KNOB_RADIUS: f32 = 7.0;
+
+/// On/off switch, flipped with Space while focused
+#[derive(Clone)]
+pub struct Toggle {
+    on: bool,
+    on_toggle: Option<Rc<dyn ToggleCb>>,
+}
+
+impl std::fmt::Debug for Toggle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct(std::any::type_name::<Self>())
+            .field("on", &self.on)
+            .field("on_toggle", &"Cool closure")
+            .finish()
+    }
+}
+
+impl Toggle {
+    pub fn new(on: bool) -> Self {
+        Self { on, on_toggle: None }
+    }
+    /// Called with the new value when the user flips it
+    pub fn on_toggle(mut self, on_toggle: impl ToggleCb + 'static) -> Self {
+        self.on_toggle = Some(Rc::new(on_toggle));
+        self
+    }
+    pub fn is_on(&self) -> bool {
+        self.on
+    }
+    /// Doesn't call the toggle handler
+    pub fn set_on(&mut self, on: bool) {
+        self.on = on;
+    }
+    pub fn toggle(&mut self, ctx: &mut EventCtx) {
+        self.on = !self.on;
+        ctx.request_redraw();
+        if let Some(on_toggle) = self.on_toggle.clone() {
+            let on = self.on;
+            ctx.defer(move || on_toggle(on));
+        }
+    }
+}
+
+impl Widget for Toggle {
+    fn measure(&mut self, _theme: &Theme) -> Size {
+        TRACK
+    }
+    fn draw(&mut self, ctx: &mut DrawCtx) {
+        let (theme, state, rect) = (ctx.theme, ctx.state, ctx.rect);
+        let track = Rect::new(rect.x, rect.y + (rect.height - TRACK.height) / 2, TRACK.width, TRACK.height);
+        let fill = match state {
+            _ if state.disabled => theme.surface,
+            _ if self.on && state.pressed => theme.accent.lerp(&theme.surface_pressed, 0.5),
+            // The track is already surface_pressed when off, so a hint of the accent it turns to
+            _ if state.pressed => theme.surface_pressed.lerp(&theme.accent, 0.25),
+            _ if self.on && state.hovered => theme.accent_hover,
+            _ if self.on => theme.accent,
+            _ if state.hovered => theme.surface_hover,
+            _ => theme.surface_pressed,
+        };
+        ctx.canvas.fill_rect(track, fill);
+        let margin = TRACK.height as f32 / 2.0;
+        let cx = if self.on { (track.x + track.width) as f32 - margin } else { track.x as f32 + margin };
+        let knob = if state.disabled { theme.text_disabled } else { theme.on_accent };
+        ctx.canvas.fill_circle(cx, track.y as f32 + margin, KNOB_RADIUS, knob);
+    }
+    fn focusable(&self) -> bool {
+        true
+    }
+    fn event(&mut self, evt: WidgetEvent, ctx: &mut EventCtx) {
+        match evt {
+            WidgetEvent::PointerButton { button: BTN_LEFT, pressed: false, .. } if ctx.state.hovered => {
+                self.toggle(ctx)
+            }
+            WidgetEvent::Key(key) if key.pressed && key.keysym == keysyms::XKB_KEY_space => self.toggle(ctx),
+            WidgetEvent::Activate => self.toggle(ctx),
+            _ => (),
+        }
+    }
+}
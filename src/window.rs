@@ -1,28 +1,60 @@
 
 
-use rgb::alt::ARGB8;
+use std::cell::Cell;
+use std::rc::Rc;
 
 use smithay_client_toolkit::shm::AutoMemPool;
 use wayland_client::{
-    protocol::{wl_surface::WlSurface},
+    protocol::{wl_callback, wl_compositor::WlCompositor, wl_region::WlRegion, wl_surface::WlSurface},
     Main,
 };
+use wayland_protocols::unstable::idle_inhibit::v1::client::{
+    zwp_idle_inhibit_manager_v1::ZwpIdleInhibitManagerV1, zwp_idle_inhibitor_v1::ZwpIdleInhibitorV1,
+};
 
 
+use crate::renderer::{self, FrameBuffer, Pixel, PixelFormat};
+use crate::widget::WidgetTree;
 use crate::{globals::BackendGlobals, prelude::*};
 
 pub mod xdg_shell;
+pub mod pointer_constraints;
+pub mod shortcuts_inhibit;
 
-// Assumed to br XRGB
+/// Premultiplied, as in Argb8888 buffers
 pub struct Pixels<'a> {
-    pub buf: &'a [ARGB8],
+    pub buf: &'a [Pixel],
     pub width: usize,
 }
 
+/// Builds a region out of `rects`. The caller should destroy it once it has been used.
+pub fn create_region(wl_compositor: &WlCompositor, rects: &[Rect]) -> WlRegion {
+    let region = wl_compositor.create_region();
+    for rect in rects {
+        region.add(rect.x, rect.y, rect.width, rect.height);
+    }
+    region.detach()
+}
+
 #[derive(Debug, Clone)]
 pub struct WindowCommon {
     shm_pool: RcCell<AutoMemPool>,
+    wl_compositor: Main<WlCompositor>,
     surface: Main<WlSurface>,
+    format: PixelFormat,
+    /// Last sent with `set_opaque_region`
+    opaque_region: Vec<Rect>,
+    /// Last sent with `set_input_region`
+    input_region: Vec<Rect>,
+    /// 0 keeps the corners square
+    corner_radius: i32,
+    idle_inhibit_manager: Option<Main<ZwpIdleInhibitManagerV1>>,
+    idle_inhibitor: Option<Main<ZwpIdleInhibitorV1>>,
+    widgets: RcCell<WidgetTree>,
+    /// What was last drawn, the widgets only repaint what changed
+    frame: RcCell<FrameBuffer>,
+    /// A frame callback was requested and didn't fire yet
+    frame_pending: Rc<Cell<bool>>,
     should_close: bool,
     width: i32,
     height: i32,
@@ -31,46 +63,150 @@ pub struct WindowCommon {
 impl WindowCommon {
     pub fn new(globals: GlobalsHandle<impl BackendGlobals>) -> Self {
         let surface = globals.wl_compositor.create_surface();
+        let mut widgets = WidgetTree::default();
+        widgets.set_theme(globals.theme());
 
         Self {
             shm_pool: globals.shm_pool.clone(),
+            wl_compositor: globals.wl_compositor.clone(),
             surface,
+            format: globals.pixel_format().expect("No pixel format to draw windows in"),
+            opaque_region: Vec::new(),
+            input_region: Vec::new(),
+            corner_radius: 0,
+            idle_inhibit_manager: globals.idle_inhibit_manager.clone(),
+            idle_inhibitor: None,
+            widgets: RcCell::new(widgets),
+            frame: RcCell::default(),
+            frame_pending: Rc::default(),
             width: 0,
             height: 0,
             should_close: false,
         }
     }
     pub fn resize(&mut self, width: i32, height: i32) {
+        self.width = width;
+        self.height = height;
+        self.widgets.borrow_mut().resize(width, height);
+        self.redraw();
+    }
+    /// Rounds the corners of the window, clipping its content. Takes effect on the next redraw.
+    pub fn set_corner_radius(&mut self, radius: i32) {
+        if radius == self.corner_radius {
+            return;
+        }
+        self.corner_radius = radius;
+        // The corners were cut at the old radius in what was last drawn
+        self.widgets.borrow_mut().request_redraw();
+    }
+    /// Draws what changed in the widgets into a new buffer, the rest is copied from the
+    /// last one. Returns whether the surface was committed.
+    pub fn redraw(&mut self) -> bool {
+        let (width, height) = (self.width, self.height);
+        if width <= 0 || height <= 0 {
+            return false;
+        }
+        let mut frame = self.frame.borrow_mut();
+        let mut widgets = self.widgets.borrow_mut();
+        if frame.resize(width as usize, height as usize) {
+            widgets.request_redraw();
+        }
+        let bounds = Rect::new(0, 0, width, height);
+        let mut canvas = frame.canvas();
+        let damage = widgets.draw(&mut canvas);
+        if damage.is_empty() {
+            return false;
+        }
+        if self.corner_radius > 0 {
+            // Only the damage was drawn again, the rest is already cut
+            for rect in damage.rects() {
+                canvas.set_clip(Some(*rect));
+                canvas.round_corners(bounds, self.corner_radius);
+            }
+        }
+
         let mut shm_pool = self.shm_pool.borrow_mut();
         let (buf, wlbuf) = shm_pool
             .buffer(
                 width,
                 height,
-                width * 4,
-                wayland_client::protocol::wl_shm::Format::Xrgb8888,
+                width * self.format.bytes_per_pixel() as i32,
+                self.format.wl_format(),
                 )
             .unwrap();
-        for (i, pixels) in buf.chunks_exact_mut(4).enumerate() {
-            let r = (i % width as usize) * 255 / width as usize;
-            let g = i*255 / (width * height) as usize;
-            let bor = 255usize;
-
-            let mut b = bor.overflowing_sub(r);
-            if b.1 {
-                b = r.overflowing_sub(bor);
+        // The pool hands out fresh memory for each commit, so the whole frame is copied into
+        // it. That is a plain copy; converting to another format, which costs more, only
+        // happens in the damage.
+        buf.copy_from_slice(frame.converted(self.format, damage.rects()));
+        let opaque_region = match self.format.has_alpha() {
+            true => {
+                let shape = renderer::rounded_rects(bounds, self.corner_radius, 1.0);
+                let opaque = widgets.opaque_region();
+                opaque
+                    .iter()
+                    .flat_map(|opaque| shape.iter().map(|rect| rect.intersect(*opaque)))
+                    .filter(|rect| !rect.is_empty())
+                    .collect()
             }
-            pixels[0] = b.0 as u8;
-            pixels[1] = g as u8;
-            pixels[2] = r as u8;
+            false => vec![bounds],
+        };
+        if opaque_region != self.opaque_region {
+            let region = create_region(&self.wl_compositor, &opaque_region);
+            self.surface.set_opaque_region(Some(&region));
+            region.destroy();
+            self.opaque_region = opaque_region;
+        }
+        // Clicks on the cut corners go to what is below
+        let input_region = renderer::rounded_rects(bounds, self.corner_radius, 0.5);
+        if input_region != self.input_region {
+            let region = create_region(&self.wl_compositor, &input_region);
+            self.surface.set_input_region(Some(&region));
+            region.destroy();
+            self.input_region = input_region;
         }
 
         self.surface.attach(Some(&wlbuf), 0, 0);
-        self.surface.damage_buffer(0, 0, width, height);
-
-        self.width = width;
-        self.height = height;
+        for rect in damage.rects() {
+            self.surface.damage_buffer(rect.x, rect.y, rect.width, rect.height);
+        }
 
         self.surface.commit();
+        true
+    }
+    /// Calls `on_frame` when the compositor is ready for the next frame, which it tells
+    /// after the next commit. Returns `false` if a frame is already requested.
+    pub fn request_frame(&mut self, on_frame: impl FnOnce() + 'static) -> bool {
+        if self.frame_pending.get() {
+            return false;
+        }
+        self.frame_pending.set(true);
+        let pending = self.frame_pending.clone();
+        let mut on_frame = Some(on_frame);
+        self.surface.frame().quick_assign(move |_, evt, _| {
+            let wl_callback::Event::Done { .. } = evt else {
+                return;
+            };
+            pending.set(false);
+            if let Some(on_frame) = on_frame.take() {
+                on_frame();
+            }
+        });
+        true
+    }
+    pub fn widgets(&self) -> RcCell<WidgetTree> {
+        self.widgets.clone()
+    }
+    /// Returns `false` if the compositor can't inhibit idling.
+    pub fn set_idle_inhibit(&mut self, inhibit: bool) -> bool {
+        let Some(manager) = &self.idle_inhibit_manager else {
+            return false;
+        };
+        match (inhibit, self.idle_inhibitor.take()) {
+            (true, None) => self.idle_inhibitor = Some(manager.create_inhibitor(&self.surface)),
+            (false, Some(inhibitor)) => inhibitor.destroy(),
+            (_, inhibitor) => self.idle_inhibitor = inhibitor,
+        }
+        true
     }
 }
 
@@ -81,4 +217,15 @@ pub trait WindowBackend: Clone {
         self.window_common().should_close
     }
     fn window_common(&self) -> WindowCommon;
+    fn widgets(&self) -> RcCell<WidgetTree> {
+        self.window_common().widgets()
+    }
+    /// Draws the window again if its widgets changed
+    fn update(&self);
+    /// Keeps the screen from blanking while the window is visible. Returns `false` if the
+    /// compositor can't do it.
+    fn set_idle_inhibit(&self, inhibit: bool) -> bool;
+    fn is_idle_inhibited(&self) -> bool {
+        self.window_common().idle_inhibitor.is_some()
+    }
 }
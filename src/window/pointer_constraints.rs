@@ -0,0 +1,196 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use wayland_client::protocol::wl_compositor::WlCompositor;
+use wayland_client::protocol::wl_pointer::WlPointer;
+use wayland_client::protocol::wl_region::WlRegion;
+use wayland_client::protocol::wl_surface::WlSurface;
+use wayland_client::Main;
+use wayland_protocols::unstable::pointer_constraints::v1::client::{
+    zwp_confined_pointer_v1::{self, ZwpConfinedPointerV1},
+    zwp_locked_pointer_v1::{self, ZwpLockedPointerV1},
+    zwp_pointer_constraints_v1::ZwpPointerConstraintsV1,
+};
+use wayland_protocols::unstable::relative_pointer::v1::client::zwp_relative_pointer_v1::{
+    self, ZwpRelativePointerV1,
+};
+
+pub use wayland_protocols::unstable::pointer_constraints::v1::client::zwp_pointer_constraints_v1::Lifetime;
+
+use crate::{globals::BackendGlobals, prelude::*};
+
+use super::create_region;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConstraintState {
+    Active,
+    Inactive,
+}
+
+pub trait ConstraintCb = Fn(ConstraintState);
+pub trait RelativeMotionCb = Fn(RelativeMotion);
+
+#[derive(Debug)]
+enum ConstraintProxy {
+    Locked(Main<ZwpLockedPointerV1>),
+    Confined(Main<ZwpConfinedPointerV1>),
+}
+
+/// A pointer lock or confinement. It is released when dropped.
+#[derive(Debug)]
+pub struct PointerConstraint {
+    proxy: ConstraintProxy,
+    wl_compositor: Main<WlCompositor>,
+    active: Rc<Cell<bool>>,
+}
+
+impl PointerConstraint {
+    /// Stops the pointer from moving while `surface` has focus. Motion keeps coming
+    /// through [`RelativePointer`].
+    pub fn lock(
+        globals: &GlobalsHandle<impl BackendGlobals>,
+        surface: &WlSurface,
+        pointer: &WlPointer,
+        lifetime: Lifetime,
+        region: Option<&[Rect]>,
+        on_state: impl ConstraintCb + 'static,
+    ) -> Option<Self> {
+        Self::new(globals, region, on_state, |constraints, region| {
+            ConstraintProxy::Locked(constraints.lock_pointer(surface, pointer, region, lifetime))
+        })
+    }
+    /// Keeps the pointer inside `region`, or the whole input region of `surface`.
+    pub fn confine(
+        globals: &GlobalsHandle<impl BackendGlobals>,
+        surface: &WlSurface,
+        pointer: &WlPointer,
+        lifetime: Lifetime,
+        region: Option<&[Rect]>,
+        on_state: impl ConstraintCb + 'static,
+    ) -> Option<Self> {
+        Self::new(globals, region, on_state, |constraints, region| {
+            ConstraintProxy::Confined(constraints.confine_pointer(surface, pointer, region, lifetime))
+        })
+    }
+    /// `request` makes the constraint, given the region built from `region`
+    fn new(
+        globals: &GlobalsHandle<impl BackendGlobals>,
+        region: Option<&[Rect]>,
+        on_state: impl ConstraintCb + 'static,
+        request: impl FnOnce(&ZwpPointerConstraintsV1, Option<&WlRegion>) -> ConstraintProxy,
+    ) -> Option<Self> {
+        let constraints = globals.pointer_constraints.as_ref()?;
+        let region = region.map(|rects| create_region(&globals.wl_compositor, rects));
+        let proxy = request(constraints, region.as_ref());
+        if let Some(region) = region {
+            region.destroy();
+        }
+        let active = Rc::new(Cell::new(false));
+        let set_state = {
+            let active = active.clone();
+            move |state| {
+                active.set(state == ConstraintState::Active);
+                on_state(state)
+            }
+        };
+        match &proxy {
+            ConstraintProxy::Locked(locked) => locked.quick_assign(move |_, evt, _| match evt {
+                zwp_locked_pointer_v1::Event::Locked => set_state(ConstraintState::Active),
+                zwp_locked_pointer_v1::Event::Unlocked => set_state(ConstraintState::Inactive),
+                _ => {}
+            }),
+            ConstraintProxy::Confined(confined) => confined.quick_assign(move |_, evt, _| match evt {
+                zwp_confined_pointer_v1::Event::Confined => set_state(ConstraintState::Active),
+                zwp_confined_pointer_v1::Event::Unconfined => set_state(ConstraintState::Inactive),
+                _ => {}
+            }),
+        }
+        Some(Self {
+            proxy,
+            wl_compositor: globals.wl_compositor.clone(),
+            active,
+        })
+    }
+    pub fn is_active(&self) -> bool {
+        self.active.get()
+    }
+    /// Takes effect on the next commit of the constrained surface.
+    pub fn set_region(&self, region: Option<&[Rect]>) {
+        let region = region.map(|rects| create_region(&self.wl_compositor, rects));
+        match &self.proxy {
+            ConstraintProxy::Locked(locked) => locked.set_region(region.as_ref()),
+            ConstraintProxy::Confined(confined) => confined.set_region(region.as_ref()),
+        }
+        if let Some(region) = region {
+            region.destroy();
+        }
+    }
+    /// Where the compositor should put the cursor when a lock ends. Ignored for confinements.
+    pub fn set_cursor_position_hint(&self, x: f64, y: f64) {
+        if let ConstraintProxy::Locked(locked) = &self.proxy {
+            locked.set_cursor_position_hint(x, y);
+        }
+    }
+}
+
+impl Drop for PointerConstraint {
+    fn drop(&mut self) {
+        match &self.proxy {
+            ConstraintProxy::Locked(locked) => locked.destroy(),
+            ConstraintProxy::Confined(confined) => confined.destroy(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RelativeMotion {
+    /// Timestamp in microseconds
+    pub time: u64,
+    pub dx: f64,
+    pub dy: f64,
+    pub dx_unaccel: f64,
+    pub dy_unaccel: f64,
+}
+
+/// Pointer deltas, reported even while the pointer is locked or at the screen edge.
+#[derive(Debug)]
+pub struct RelativePointer {
+    proxy: Main<ZwpRelativePointerV1>,
+}
+
+impl RelativePointer {
+    pub fn new(
+        globals: &GlobalsHandle<impl BackendGlobals>,
+        pointer: &WlPointer,
+        on_motion: impl RelativeMotionCb + 'static,
+    ) -> Option<Self> {
+        let manager = globals.relative_pointer_manager.as_ref()?;
+        let proxy = manager.get_relative_pointer(pointer);
+        proxy.quick_assign(move |_, evt, _| {
+            if let zwp_relative_pointer_v1::Event::RelativeMotion {
+                utime_hi,
+                utime_lo,
+                dx,
+                dy,
+                dx_unaccel,
+                dy_unaccel,
+            } = evt
+            {
+                on_motion(RelativeMotion {
+                    time: (utime_hi as u64) << 32 | utime_lo as u64,
+                    dx,
+                    dy,
+                    dx_unaccel,
+                    dy_unaccel,
+                })
+            }
+        });
+        Some(Self { proxy })
+    }
+}
+
+impl Drop for RelativePointer {
+    fn drop(&mut self) {
+        self.proxy.destroy();
+    }
+}
@@ -0,0 +1,72 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use wayland_client::protocol::wl_seat::WlSeat;
+use wayland_client::protocol::wl_surface::WlSurface;
+use wayland_client::Main;
+use wayland_protocols::unstable::keyboard_shortcuts_inhibit::v1::client::zwp_keyboard_shortcuts_inhibitor_v1::{
+    self, ZwpKeyboardShortcutsInhibitorV1,
+};
+
+use crate::{globals::BackendGlobals, prelude::*};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InhibitState {
+    Active,
+    Inactive,
+}
+
+pub trait InhibitCb = Fn(InhibitState);
+
+/// Asks the compositor to send its keyboard shortcuts to a surface instead of handling them.
+/// The shortcuts are restored when dropped.
+#[derive(Debug)]
+pub struct ShortcutsInhibitor {
+    seat: WlSeat,
+    proxy: Main<ZwpKeyboardShortcutsInhibitorV1>,
+    active: Rc<Cell<bool>>,
+}
+
+impl ShortcutsInhibitor {
+    /// Only one inhibitor may exist per surface and seat.
+    pub fn new(
+        globals: &GlobalsHandle<impl BackendGlobals>,
+        surface: &WlSurface,
+        seat: &WlSeat,
+        on_state: impl InhibitCb + 'static,
+    ) -> Option<Self> {
+        let manager = globals.shortcuts_inhibit_manager.as_ref()?;
+        let proxy = manager.inhibit_shortcuts(surface, seat);
+        let active = Rc::new(Cell::new(false));
+        proxy.quick_assign({
+            let active = active.clone();
+            move |_, evt, _| {
+                let state = match evt {
+                    zwp_keyboard_shortcuts_inhibitor_v1::Event::Active => InhibitState::Active,
+                    zwp_keyboard_shortcuts_inhibitor_v1::Event::Inactive => InhibitState::Inactive,
+                    _ => return,
+                };
+                active.set(state == InhibitState::Active);
+                on_state(state)
+            }
+        });
+        Some(Self {
+            seat: seat.clone(),
+            proxy,
+            active,
+        })
+    }
+    pub fn seat(&self) -> &WlSeat {
+        &self.seat
+    }
+    /// The compositor may refuse or later cancel the inhibition, e.g. when the user asks for it.
+    pub fn is_active(&self) -> bool {
+        self.active.get()
+    }
+}
+
+impl Drop for ShortcutsInhibitor {
+    fn drop(&mut self) {
+        self.proxy.destroy();
+    }
+}
@@ -3,8 +3,14 @@ mod globals;
 mod window;
 mod cursor;
 mod frame;
+mod gestures;
+mod tablet;
+mod text_input;
 
 pub use window::XdgWindow;
 pub use globals::XdgGlobals;
+pub use gestures::GestureEvent;
+pub use tablet::{TabletEvent, ToolEvent};
+pub use text_input::{ContentHint, ContentPurpose, Preedit, TextInput, TextInputClient};
 
 pub type GlobalsHandle = super::GlobalsHandle<XdgGlobals>;
@@ -1,43 +1,234 @@
+use std::rc::Rc;
+
+use super::gestures::{bind_gestures, GestureCb, GestureEvent};
 use super::{GlobalsHandle, globals};
 use crate::get_globals;
 use crate::prelude::RcCell;
+use crate::widget::PointerEvent;
+use wayland_client::protocol::wl_callback;
 use wayland_client::protocol::wl_pointer::{self, WlPointer};
 use wayland_client::protocol::wl_surface::WlSurface;
 use wayland_client::Main;
+use wayland_cursor::CursorImageBuffer;
+use wayland_protocols::unstable::tablet::v2::client::zwp_tablet_tool_v2::ZwpTabletToolV2;
+
+use crate::protocols::cursor_shape::wp_cursor_shape_device_v1::{Shape, WpCursorShapeDeviceV1};
+
+/// Groups of names meaning the same cursor. Themes only ship some of them, either the
+/// legacy X names or the CSS ones, so every name of a group is tried before giving up.
+const CURSOR_ALIASES: &[&[&str]] = &[
+    &["left_ptr", "default", "arrow", "top_left_arrow"],
+    &["watch", "wait"],
+    &["left_ptr_watch", "progress", "half-busy"],
+    &["xterm", "text", "ibeam"],
+    &["hand2", "pointer", "hand1", "pointing_hand"],
+    &["fleur", "move", "all-scroll", "size_all"],
+    &["crosshair", "cross", "tcross"],
+    &["question_arrow", "help", "whats_this"],
+    &["X_cursor", "not-allowed", "crossed_circle", "forbidden"],
+    &["top_left_corner", "nw-resize", "nwse-resize", "size_fdiag"],
+    &["top_right_corner", "ne-resize", "nesw-resize", "size_bdiag"],
+    &["bottom_left_corner", "sw-resize", "nesw-resize", "size_bdiag"],
+    &["bottom_right_corner", "se-resize", "nwse-resize", "size_fdiag"],
+    &["top_side", "n-resize", "ns-resize"],
+    &["bottom_side", "s-resize", "ns-resize"],
+    &["left_side", "w-resize", "ew-resize"],
+    &["right_side", "e-resize", "ew-resize"],
+    &["sb_h_double_arrow", "ew-resize", "col-resize", "size_hor"],
+    &["sb_v_double_arrow", "ns-resize", "row-resize", "size_ver"],
+];
+
+/// Every name worth trying for `name`, ending with the default arrow.
+fn cursor_names(name: &str) -> impl Iterator<Item = &str> {
+    let aliases = CURSOR_ALIASES
+        .iter()
+        .find(|group| group.contains(&name))
+        .copied()
+        .unwrap_or(&[]);
+    std::iter::once(name)
+        .chain(aliases.iter().copied())
+        .chain(CURSOR_ALIASES[0].iter().copied())
+}
 
+/// The cursor-shape-v1 equivalent of a cursor name.
+fn cursor_shape(name: &str) -> Option<Shape> {
+    cursor_names(name).find_map(|name| {
+        let shape = match name {
+            "default" => Shape::Default,
+            "context-menu" => Shape::ContextMenu,
+            "help" => Shape::Help,
+            "pointer" => Shape::Pointer,
+            "progress" => Shape::Progress,
+            "wait" => Shape::Wait,
+            "cell" => Shape::Cell,
+            "crosshair" => Shape::Crosshair,
+            "text" => Shape::Text,
+            "vertical-text" => Shape::VerticalText,
+            "alias" => Shape::Alias,
+            "copy" => Shape::Copy,
+            "move" => Shape::Move,
+            "no-drop" => Shape::NoDrop,
+            "not-allowed" => Shape::NotAllowed,
+            "grab" => Shape::Grab,
+            "grabbing" => Shape::Grabbing,
+            "e-resize" => Shape::EResize,
+            "n-resize" => Shape::NResize,
+            "ne-resize" => Shape::NeResize,
+            "nw-resize" => Shape::NwResize,
+            "s-resize" => Shape::SResize,
+            "se-resize" => Shape::SeResize,
+            "sw-resize" => Shape::SwResize,
+            "w-resize" => Shape::WResize,
+            "ew-resize" => Shape::EwResize,
+            "ns-resize" => Shape::NsResize,
+            "nesw-resize" => Shape::NeswResize,
+            "nwse-resize" => Shape::NwseResize,
+            "col-resize" => Shape::ColResize,
+            "row-resize" => Shape::RowResize,
+            "all-scroll" => Shape::AllScroll,
+            "zoom-in" => Shape::ZoomIn,
+            "zoom-out" => Shape::ZoomOut,
+            _ => return None,
+        };
+        Some(shape)
+    })
+}
 
 #[derive(Debug)]
-pub struct CursorFrame {
+struct CursorFrameInner {
     surface: Main<WlSurface>,
-    hotspot: (u32, u32),
+    images: Vec<CursorImageBuffer>,
+    current: usize,
+    // Compositor timestamp of when `current` got displayed
+    shown_at: Option<u32>,
+}
+
+impl CursorFrameInner {
+    fn show(&mut self, index: usize) {
+        let image = &self.images[index];
+        let (width, height) = image.dimensions();
+        self.surface.attach(Some(image), 0, 0);
+        self.surface.damage_buffer(0, 0, width as i32, height as i32);
+        self.current = index;
+    }
+    fn is_animated(&self) -> bool {
+        self.images.len() > 1
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CursorFrame {
+    inner: RcCell<CursorFrameInner>,
 }
 
 impl CursorFrame {
     pub fn new(globals: &GlobalsHandle, name: &str) -> Option<Self> {
         let mut cursor_theme = globals.backend.cursor_theme.borrow_mut();
-        let cursor_image = &cursor_theme.get_cursor(name)?[0];
-        let cursor_surface = globals.wl_compositor.create_surface();
-        let (width, height) = cursor_image.dimensions();
-        cursor_surface.attach(Some(&cursor_image), 0, 0);
-        cursor_surface.damage_buffer(0, 0, width as i32, height as i32);
-        cursor_surface.commit();
-        Some(Self { surface: cursor_surface, hotspot: cursor_image.hotspot() })
+        let name = cursor_names(name).find(|name| cursor_theme.get_cursor(name).is_some())?;
+        let cursor = cursor_theme.get_cursor(name)?;
+        let images = (0..cursor.image_count()).map(|i| cursor[i].clone()).collect();
+        let this = Self {
+            inner: CursorFrameInner {
+                surface: globals.wl_compositor.create_surface(),
+                images,
+                current: 0,
+                shown_at: None,
+            }
+            .into(),
+        };
+        this.inner.borrow_mut().show(0);
+        if this.inner.borrow().is_animated() {
+            this.request_frame();
+        }
+        this.inner.borrow().surface.commit();
+        Some(this)
     }
     pub fn set(&self, serial: u32, ptr: &WlPointer) {
-        let (hx, hy) = self.hotspot;
-        ptr.set_cursor(serial, Some(&self.surface), hx as i32, hy as i32);
+        let inner = self.inner.borrow();
+        let (hx, hy) = inner.images[inner.current].hotspot();
+        ptr.set_cursor(serial, Some(&inner.surface), hx as i32, hy as i32);
+    }
+    pub fn set_tool(&self, serial: u32, tool: &ZwpTabletToolV2) {
+        let inner = self.inner.borrow();
+        let (hx, hy) = inner.images[inner.current].hotspot();
+        tool.set_cursor(serial, Some(&inner.surface), hx as i32, hy as i32);
+    }
+    /// Frame callbacks only fire while the cursor is on screen, so the animation
+    /// pauses by itself whenever the cursor is hidden.
+    fn request_frame(&self) {
+        let weak = self.inner.weak();
+        self.inner.borrow().surface.frame().quick_assign(move |_, evt, _| {
+            if let wl_callback::Event::Done { callback_data } = evt {
+                if let Some(inner) = weak.try_strong() {
+                    CursorFrame { inner }.advance(callback_data);
+                }
+            }
+        });
+    }
+    fn advance(&self, time: u32) {
+        {
+            let mut inner = self.inner.borrow_mut();
+            let shown_at = *inner.shown_at.get_or_insert(time);
+            let delay = inner.images[inner.current].delay();
+            if time.wrapping_sub(shown_at) >= delay {
+                let next = (inner.current + 1) % inner.images.len();
+                inner.show(next);
+                inner.shown_at = Some(time);
+            }
+        }
+        self.request_frame();
+        self.inner.borrow().surface.commit();
+    }
+}
+
+/// Where the pointer image comes from
+#[derive(Debug)]
+enum PointerCursor {
+    /// Drawn by the compositor, through cursor-shape-v1
+    Shape(Main<WpCursorShapeDeviceV1>, Shape),
+    /// Drawn by us, from the cursor theme
+    Frame(CursorFrame),
+    Hidden,
+}
+
+impl PointerCursor {
+    fn new(globals: &GlobalsHandle, name: &str, ptr: &WlPointer) -> Self {
+        if let Some(manager) = &globals.backend.cursor_shape_manager {
+            if let Some(shape) = cursor_shape(name) {
+                return Self::Shape(manager.get_pointer(ptr), shape);
+            }
+        }
+        match CursorFrame::new(globals, name) {
+            Some(cursor_frame) => Self::Frame(cursor_frame),
+            None => {
+                eprintln!("Cursor {} not found in theme, the pointer will be hidden", name);
+                Self::Hidden
+            }
+        }
+    }
+    fn set(&self, serial: u32, ptr: &WlPointer) {
+        match self {
+            Self::Shape(device, shape) => device.set_shape(serial, *shape),
+            Self::Frame(cursor_frame) => cursor_frame.set(serial, ptr),
+            Self::Hidden => ptr.set_cursor(serial, None, 0, 0),
+        }
     }
 }
 
 trait ClickedCb = Fn(i32, i32);
+pub trait PointerCb = Fn(PointerEvent);
 
 struct PointerInfoInner {
     wl_surface: WlSurface,
-    cursor_frame: CursorFrame,
+    wl_pointer: Main<WlPointer>,
+    cursor: PointerCursor,
     cursor_pos: (f64, f64),
     serial: u32,
     is_inside: bool,
     clicked: Option<Box<dyn ClickedCb>>,
+    in_gesture: bool,
+    gesture: Option<Rc<dyn GestureCb>>,
+    pointer_event: Option<Rc<dyn PointerCb>>,
 }
 
 impl std::fmt::Debug for PointerInfo {
@@ -45,17 +236,19 @@ impl std::fmt::Debug for PointerInfo {
         let inner = self.inner.borrow();
         f.debug_struct(std::any::type_name::<Self>())
             .field("wl_surface", &inner.wl_surface)
-            .field("cursor_frame", &inner.cursor_frame)
+            .field("cursor", &inner.cursor)
             .field("cursor_pos", &inner.cursor_pos)
             .field("serial", &inner.serial)
             .field("is_inside", &inner.is_inside)
             .field("clicked", &"Cool closure")
+            .field("in_gesture", &inner.in_gesture)
             .finish()
     }
 }
 
 impl PointerInfoInner {
-    fn handle_pointer_event(&mut self, evt: wl_pointer::Event, ptr: Main<WlPointer>) {
+    /// Returns what to pass on to the widgets, if it happened on our surface
+    fn handle_pointer_event(&mut self, evt: wl_pointer::Event, ptr: Main<WlPointer>) -> Option<PointerEvent> {
         match evt {
             wl_pointer::Event::Enter {
                 serial,
@@ -67,7 +260,8 @@ impl PointerInfoInner {
                     self.is_inside = true;
                     self.update_pointer_pos(x, y);
                     self.serial = serial;
-                    self.cursor_frame.set(self.serial, &ptr)
+                    self.cursor.set(self.serial, &ptr);
+                    return Some(PointerEvent::Motion { x, y });
                 }
             }
             wl_pointer::Event::Motion {
@@ -77,24 +271,49 @@ impl PointerInfoInner {
             } => {
                 if self.is_inside {
                     self.update_pointer_pos(x, y);
+                    return Some(PointerEvent::Motion { x, y });
                 }
             }
-            wl_pointer::Event::Button { serial, time, button, state } => {
+            wl_pointer::Event::Button { serial, button, state, .. } => {
                 self.serial = serial;
+                if self.is_inside {
+                    let pressed = state == wl_pointer::ButtonState::Pressed;
+                    return Some(PointerEvent::Button { button, pressed, serial });
+                }
             }
             wl_pointer::Event::Leave { serial, surface } => {
                 if self.is_inside && surface == self.wl_surface {
                     self.is_inside = false;
                     self.serial = serial;
-                    ptr.set_cursor(self.serial, None, 0, 0)
+                    ptr.set_cursor(self.serial, None, 0, 0);
+                    return Some(PointerEvent::Leave);
                 }
             }
             _ => (),
         }
+        None
     }
     fn update_pointer_pos(&mut self, x: f64, y: f64) {
         self.cursor_pos = (x, y);
     }
+    /// Returns the callback to run, if the gesture happens on our surface. It is called
+    /// by the caller so it can use the `PointerInfo`.
+    fn handle_gesture_event(
+        &mut self,
+        evt: GestureEvent,
+        surface: Option<WlSurface>,
+    ) -> Option<Rc<dyn GestureCb>> {
+        if let Some(surface) = surface {
+            self.in_gesture = surface == self.wl_surface;
+        }
+        if !self.in_gesture {
+            return None;
+        }
+        if evt.is_end() {
+            self.in_gesture = false;
+        }
+        self.gesture.clone()
+    }
 }
 
 #[derive(Clone)]
@@ -105,23 +324,40 @@ pub struct PointerInfo {
 impl PointerInfo {
     pub fn new(cursor_name: &str, parent_surface: WlSurface) -> Self {
         let globals = get_globals();
-        let Some(cursor_frame) = CursorFrame::new(&globals, cursor_name) else {
-            panic!("Cursor surface {} not found", cursor_name);
-        };
+        let wl_pointer = globals.wl_seat.get_pointer();
         let this = Self {
             inner: PointerInfoInner {
                 wl_surface: parent_surface,
-                cursor_frame,
+                cursor: PointerCursor::new(globals, cursor_name, &wl_pointer),
+                wl_pointer: wl_pointer.clone(),
                 cursor_pos: (0.0, 0.0),
                 serial: 0,
                 is_inside: false,
                 clicked: None,
+                in_gesture: false,
+                gesture: None,
+                pointer_event: None,
             }
             .into(),
         };
-        globals.wl_seat.get_pointer().quick_assign({
+        wl_pointer.quick_assign({
+            let this = this.clone();
+            move |ptr, evt, _| {
+                let evt = this.inner.borrow_mut().handle_pointer_event(evt, ptr);
+                let pointer_event = this.inner.borrow().pointer_event.clone();
+                if let (Some(evt), Some(pointer_event)) = (evt, pointer_event) {
+                    pointer_event(evt)
+                }
+            }
+        });
+        bind_gestures(globals, &wl_pointer, {
             let this = this.clone();
-            move |ptr, evt, _| this.inner.borrow_mut().handle_pointer_event(evt, ptr)
+            move |evt, surface| {
+                let gesture = this.inner.borrow_mut().handle_gesture_event(evt, surface);
+                if let Some(gesture) = gesture {
+                    gesture(evt)
+                }
+            }
         });
         this
     }
@@ -129,6 +365,14 @@ impl PointerInfo {
          self.inner.borrow_mut().clicked = Some(Box::new(on_click));
          self
     }
+    pub fn on_gesture(self, on_gesture: impl GestureCb + 'static) -> Self {
+        self.inner.borrow_mut().gesture = Some(Rc::new(on_gesture));
+        self
+    }
+    pub fn on_pointer_event(self, on_event: impl PointerCb + 'static) -> Self {
+        self.inner.borrow_mut().pointer_event = Some(Rc::new(on_event));
+        self
+    }
     pub fn cursor_pos(&self) -> (f64, f64) {
         self.inner.borrow().cursor_pos
     }
@@ -138,6 +382,9 @@ impl PointerInfo {
     pub fn is_inside(&self) -> bool {
         self.inner.borrow().is_inside
     }
+    pub fn wl_pointer(&self) -> Main<WlPointer> {
+        self.inner.borrow().wl_pointer.clone()
+    }
 
     pub fn update_serial(&self, serial: u32) {
         self.inner.borrow_mut().serial = serial;
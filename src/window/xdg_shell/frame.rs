@@ -1,10 +1,12 @@
 use smithay_client_toolkit::shm::AutoMemPool;
-use wayland_client::protocol::wl_shm;
 use wayland_client::protocol::wl_subsurface::WlSubsurface;
 use wayland_client::protocol::wl_surface::WlSurface;
+use rgb::RGBA8;
 use wayland_client::Main;
 
 use crate::prelude::*;
+use crate::renderer::{FrameBuffer, PixelFormat};
+use crate::widget::Theme;
 
 use super::GlobalsHandle;
 use super::cursor::PointerInfo;
@@ -12,6 +14,9 @@ use super::cursor::PointerInfo;
 #[derive(Debug)]
 pub struct XdgWindowFrame {
     padding: i32,
+    /// Size of the window, in its coordinates
+    window: Rect,
+    corner_radius: i32,
     // laterals: [XdgWindowBorder; 4],
     corners: [XdgWindowBorder; 4],
 }
@@ -38,10 +43,35 @@ impl XdgWindowFrame {
 
         // let laterals = todo!();
         let corners = names_dirs.map(|(name, dir)| make_border(name, dir));
-        Self { corners, padding }
+        Self {
+            corners,
+            padding,
+            window: Rect::default(),
+            corner_radius: 0,
+        }
     }
     pub fn resize(&mut self, width: i32, height: i32) {
 
+    }
+    /// Repaints the borders in the colors of `theme`, at its border width
+    pub fn set_theme(&mut self, theme: &Theme) {
+        self.padding = theme.border_width;
+        for corner in &mut self.corners {
+            corner.color = theme.frame;
+        }
+        // The corners stick out of the window by half the padding
+        self.move_(self.window.width, self.window.height);
+    }
+    /// Follows the rounded corners of the window, so the borders don't show through them
+    pub fn set_corner_radius(&mut self, radius: i32) {
+        if radius == self.corner_radius {
+            return;
+        }
+        self.corner_radius = radius;
+        for corner in &mut self.corners {
+            corner.set_window(self.window, radius);
+            corner.resize(self.padding, self.padding);
+        }
     }
     pub fn move_(&mut self, width: i32, height: i32) {
         /* let corners_pos = [
@@ -51,12 +81,14 @@ impl XdgWindowFrame {
             [1, 1], // bottom-right
         ]; */
         let parent_size = [width, height];
+        self.window = Rect::new(0, 0, width, height);
         for (i, corner) in self.corners.iter_mut().enumerate() {
             let corner = corner.dir.get_pos(id, c);
             let x = corners_pos[i][0] * parent_size[0] - (self.padding / 2);
             let y = corners_pos[i][1] * parent_size[1] - (self.padding / 2);
-            corner.resize(self.padding, self.padding);
             corner.move_(x, y);
+            corner.set_window(self.window, self.corner_radius);
+            corner.resize(self.padding, self.padding);
         }
     }
 }
@@ -135,7 +167,12 @@ struct XdgWindowBorder {
     pub wl_surface: Main<WlSurface>,
     pub wl_subsurface: Main<WlSubsurface>,
     shm_pool: RcCell<AutoMemPool>,
+    format: PixelFormat,
+    color: RGBA8,
     dir: Dir,
+    /// Shape of the window in its coordinates, cut out of the border
+    window: Rect,
+    corner_radius: i32,
 }
 
 impl XdgWindowBorder {
@@ -165,9 +202,13 @@ impl XdgWindowBorder {
             size,
             wl_subsurface,
             shm_pool: globals.shm_pool.clone(),
+            format: globals.pixel_format().expect("No pixel format to draw the frame in"),
+            color: globals.theme().frame,
             pointer_info,
             dir,
             wl_surface,
+            window: Rect::default(),
+            corner_radius: 0,
         };
         this.resize(size.0, size.1);
         this
@@ -176,12 +217,29 @@ impl XdgWindowBorder {
         self.pos = (x, y);
         self.wl_subsurface.set_position(x, y);
     }
+    /// Takes effect on the next resize
+    fn set_window(&mut self, window: Rect, corner_radius: i32) {
+        self.window = window;
+        self.corner_radius = corner_radius;
+    }
     fn resize(&mut self, width: i32, height: i32) {
+        let mut frame = FrameBuffer::default();
+        frame.resize(width.max(0) as usize, height.max(0) as usize);
+        let mut canvas = frame.canvas();
+        canvas.clear(self.color);
+        // It is below the window, and would show through its rounded corners
+        let window = Rect {
+            x: self.window.x - self.pos.0,
+            y: self.window.y - self.pos.1,
+            ..self.window
+        };
+        canvas.cut_out_rounded(window, self.corner_radius);
         let mut shm_pool = self.shm_pool.borrow_mut();
+        let stride = width * self.format.bytes_per_pixel() as i32;
         let (buf, wl_buf) = shm_pool
-            .buffer(width, height, width * 4, wl_shm::Format::Xrgb8888)
+            .buffer(width, height, stride, self.format.wl_format())
             .expect("Failed to allocate memory");
-        buf.fill(0xff);
+        buf.copy_from_slice(frame.converted(self.format, &[]));
         self.size = (width, height);
         self.wl_surface.damage_buffer(0, 0, width, height);
         self.wl_surface.attach(Some(&wl_buf), 0, 0);
@@ -0,0 +1,84 @@
+use wayland_client::protocol::wl_pointer::WlPointer;
+use wayland_client::protocol::wl_surface::WlSurface;
+
+use crate::protocols::pointer_gestures::{
+    zwp_pointer_gesture_hold_v1, zwp_pointer_gesture_pinch_v1, zwp_pointer_gesture_swipe_v1,
+};
+
+use super::GlobalsHandle;
+
+/// Touchpad gestures, in surface coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GestureEvent {
+    SwipeBegin { fingers: u32 },
+    SwipeUpdate { dx: f64, dy: f64 },
+    SwipeEnd { cancelled: bool },
+    PinchBegin { fingers: u32 },
+    /// `scale` is relative to the start of the pinch and `rotation` is the angle in
+    /// degrees, clockwise, since the last update.
+    PinchUpdate { dx: f64, dy: f64, scale: f64, rotation: f64 },
+    PinchEnd { cancelled: bool },
+    HoldBegin { fingers: u32 },
+    HoldEnd { cancelled: bool },
+}
+
+impl GestureEvent {
+    pub fn is_end(&self) -> bool {
+        matches!(
+            self,
+            Self::SwipeEnd { .. } | Self::PinchEnd { .. } | Self::HoldEnd { .. }
+        )
+    }
+}
+
+pub trait GestureCb = Fn(GestureEvent);
+
+/// Listens for gestures on `ptr`. `handler` also gets the focused surface on begin events.
+/// Does nothing if the compositor doesn't support gestures, hold gestures need version 3.
+pub fn bind_gestures(
+    globals: &GlobalsHandle,
+    ptr: &WlPointer,
+    handler: impl Fn(GestureEvent, Option<WlSurface>) + Clone + 'static,
+) {
+    let Some(manager) = &globals.pointer_gestures else {
+        return;
+    };
+    manager.get_swipe_gesture(ptr).quick_assign({
+        let handler = handler.clone();
+        move |_, evt, _| match evt {
+            zwp_pointer_gesture_swipe_v1::Event::Begin { surface, fingers, .. } => {
+                handler(GestureEvent::SwipeBegin { fingers }, Some(surface))
+            }
+            zwp_pointer_gesture_swipe_v1::Event::Update { dx, dy, .. } => {
+                handler(GestureEvent::SwipeUpdate { dx, dy }, None)
+            }
+            zwp_pointer_gesture_swipe_v1::Event::End { cancelled, .. } => {
+                handler(GestureEvent::SwipeEnd { cancelled: cancelled != 0 }, None)
+            }
+        }
+    });
+    manager.get_pinch_gesture(ptr).quick_assign({
+        let handler = handler.clone();
+        move |_, evt, _| match evt {
+            zwp_pointer_gesture_pinch_v1::Event::Begin { surface, fingers, .. } => {
+                handler(GestureEvent::PinchBegin { fingers }, Some(surface))
+            }
+            zwp_pointer_gesture_pinch_v1::Event::Update { dx, dy, scale, rotation, .. } => {
+                handler(GestureEvent::PinchUpdate { dx, dy, scale, rotation }, None)
+            }
+            zwp_pointer_gesture_pinch_v1::Event::End { cancelled, .. } => {
+                handler(GestureEvent::PinchEnd { cancelled: cancelled != 0 }, None)
+            }
+        }
+    });
+    if manager.as_ref().version() >= 3 {
+        manager.get_hold_gesture(ptr).quick_assign(move |_, evt, _| match evt {
+            zwp_pointer_gesture_hold_v1::Event::Begin { surface, fingers, .. } => {
+                handler(GestureEvent::HoldBegin { fingers }, Some(surface))
+            }
+            zwp_pointer_gesture_hold_v1::Event::End { cancelled, .. } => {
+                handler(GestureEvent::HoldEnd { cancelled: cancelled != 0 }, None)
+            }
+        });
+    }
+}
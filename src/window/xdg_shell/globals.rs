@@ -8,6 +8,8 @@ use wayland_client::Main;
 use wayland_cursor::CursorTheme;
 use wayland_protocols::xdg_shell::client::xdg_wm_base::{self, XdgWmBase};
 
+use crate::protocols::cursor_shape::wp_cursor_shape_manager_v1::WpCursorShapeManagerV1;
+
 use crate::{globals::BackendGlobals, prelude::RcCell, GlobalManagerExt};
 
 use super::GlobalsHandle;
@@ -16,6 +18,8 @@ use super::window::XdgWindow;
 pub struct XdgGlobals {
     pub wm_base: Main<XdgWmBase>,
     pub cursor_theme: RcCell<CursorTheme>,
+    /// Lets the compositor draw cursors itself, absent on older compositors
+    pub cursor_shape_manager: Option<Main<WpCursorShapeManagerV1>>,
 }
 
 fn get_cursor_size() -> anyhow::Result<u32> {
@@ -23,13 +27,25 @@ fn get_cursor_size() -> anyhow::Result<u32> {
     Ok(var.parse()?)
 }
 
+fn get_cursor_theme() -> anyhow::Result<String> {
+    let var = std::env::var("XCURSOR_THEME")?;
+    anyhow::ensure!(!var.is_empty(), "XCURSOR_THEME is empty");
+    Ok(var)
+}
+
 impl BackendGlobals for XdgGlobals {
     type Window = XdgWindow;
     fn new(global_manager: &wayland_client::GlobalManager) -> Rc<Self> {
         let wl_shm = global_manager.get::<wl_shm::WlShm>();
         let xdg_globals = XdgGlobals {
-            cursor_theme: CursorTheme::load(get_cursor_size().unwrap_or(24), &wl_shm).into(),
+            cursor_theme: CursorTheme::load_from_name(
+                &get_cursor_theme().unwrap_or_else(|_| "default".into()),
+                get_cursor_size().unwrap_or(24),
+                &wl_shm,
+            )
+            .into(),
             wm_base: global_manager.get(),
+            cursor_shape_manager: global_manager.instantiate_current().ok(),
         };
         xdg_globals.wm_base.quick_assign(
             |wm_base: Main<XdgWmBase>, event: xdg_wm_base::Event, _| match event {
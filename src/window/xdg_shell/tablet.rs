@@ -0,0 +1,309 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use wayland_client::protocol::wl_surface::WlSurface;
+use wayland_client::Main;
+use wayland_protocols::unstable::tablet::v2::client::{
+    zwp_tablet_pad_v2::{self, ZwpTabletPadV2},
+    zwp_tablet_seat_v2::{self, ZwpTabletSeatV2},
+    zwp_tablet_tool_v2::{self, ZwpTabletToolV2},
+    zwp_tablet_v2::{self, ZwpTabletV2},
+};
+
+pub use wayland_protocols::unstable::tablet::v2::client::zwp_tablet_tool_v2::{
+    Capability as ToolCapability, Type as ToolType,
+};
+
+use crate::get_globals;
+use crate::prelude::RcCell;
+
+use super::cursor::CursorFrame;
+
+/// Identifies a tablet, tool or pad for as long as it is plugged in
+pub type DeviceId = u32;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TabletDesc {
+    pub name: String,
+    /// USB vendor and product ids
+    pub id: Option<(u32, u32)>,
+    pub paths: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolDesc {
+    pub kind: ToolType,
+    pub hardware_serial: Option<u64>,
+    pub hardware_id_wacom: Option<u64>,
+    pub capabilities: Vec<ToolCapability>,
+}
+
+impl Default for ToolDesc {
+    fn default() -> Self {
+        Self {
+            kind: ToolType::Pen,
+            hardware_serial: None,
+            hardware_id_wacom: None,
+            capabilities: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PadDesc {
+    pub buttons: u32,
+    pub paths: Vec<String>,
+}
+
+/// Axis and button changes of a tool. They are grouped by [`ToolEvent::Frame`],
+/// which marks the end of one hardware event.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ToolEvent {
+    ProximityIn { tablet: DeviceId },
+    ProximityOut,
+    /// The tip touches the tablet
+    Down,
+    Up,
+    Motion { x: f64, y: f64 },
+    /// From 0.0 to 1.0
+    Pressure(f64),
+    /// From 0.0 to 1.0
+    Distance(f64),
+    /// In degrees, relative to the tablet's z axis
+    Tilt { x: f64, y: f64 },
+    /// In degrees, clockwise from the tool's logical neutral position
+    Rotation(f64),
+    /// From -1.0 to 1.0
+    Slider(f64),
+    Wheel { degrees: f64, clicks: i32 },
+    Button { button: u32, pressed: bool },
+    Frame { time: u32 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TabletEvent {
+    TabletAdded(DeviceId, TabletDesc),
+    TabletRemoved(DeviceId),
+    ToolAdded(DeviceId, ToolDesc),
+    ToolRemoved(DeviceId),
+    PadAdded(DeviceId, PadDesc),
+    PadRemoved(DeviceId),
+    Tool(DeviceId, ToolEvent),
+    PadButton { pad: DeviceId, button: u32, pressed: bool },
+}
+
+pub trait TabletCb = Fn(TabletEvent);
+
+#[derive(Debug, Default)]
+struct ToolState {
+    desc: ToolDesc,
+    is_inside: bool,
+    // Proximity out still has a frame event to deliver
+    is_leaving: bool,
+}
+
+#[derive(Debug, Default)]
+struct PadState {
+    desc: PadDesc,
+    is_inside: bool,
+}
+
+struct TabletInfoInner {
+    wl_surface: WlSurface,
+    cursor_frame: Option<CursorFrame>,
+    // Descriptions are only complete after the `done` event
+    tablets: HashMap<DeviceId, TabletDesc>,
+    tools: HashMap<DeviceId, ToolState>,
+    pads: HashMap<DeviceId, PadState>,
+    handler: Option<Rc<dyn TabletCb>>,
+}
+
+impl TabletInfoInner {
+    fn handle_tablet_event(&mut self, evt: zwp_tablet_v2::Event, tablet: Main<ZwpTabletV2>) -> Option<TabletEvent> {
+        let id = tablet.as_ref().id();
+        let desc = self.tablets.entry(id).or_default();
+        match evt {
+            zwp_tablet_v2::Event::Name { name } => desc.name = name,
+            zwp_tablet_v2::Event::Id { vid, pid } => desc.id = Some((vid, pid)),
+            zwp_tablet_v2::Event::Path { path } => desc.paths.push(path),
+            zwp_tablet_v2::Event::Done => return Some(TabletEvent::TabletAdded(id, desc.clone())),
+            zwp_tablet_v2::Event::Removed => {
+                self.tablets.remove(&id);
+                tablet.destroy();
+                return Some(TabletEvent::TabletRemoved(id));
+            }
+            _ => (),
+        }
+        None
+    }
+    fn handle_tool_event(&mut self, evt: zwp_tablet_tool_v2::Event, tool: Main<ZwpTabletToolV2>) -> Option<TabletEvent> {
+        let id = tool.as_ref().id();
+        let state = self.tools.entry(id).or_default();
+        let tool_event = match evt {
+            zwp_tablet_tool_v2::Event::Type { tool_type } => {
+                state.desc.kind = tool_type;
+                return None;
+            }
+            zwp_tablet_tool_v2::Event::HardwareSerial { hardware_serial_hi: hi, hardware_serial_lo: lo } => {
+                state.desc.hardware_serial = Some((hi as u64) << 32 | lo as u64);
+                return None;
+            }
+            zwp_tablet_tool_v2::Event::HardwareIdWacom { hardware_id_hi: hi, hardware_id_lo: lo } => {
+                state.desc.hardware_id_wacom = Some((hi as u64) << 32 | lo as u64);
+                return None;
+            }
+            zwp_tablet_tool_v2::Event::Capability { capability } => {
+                state.desc.capabilities.push(capability);
+                return None;
+            }
+            zwp_tablet_tool_v2::Event::Done => return Some(TabletEvent::ToolAdded(id, state.desc.clone())),
+            zwp_tablet_tool_v2::Event::Removed => {
+                self.tools.remove(&id);
+                tool.destroy();
+                return Some(TabletEvent::ToolRemoved(id));
+            }
+            zwp_tablet_tool_v2::Event::ProximityIn { serial, tablet, surface } => {
+                if surface != self.wl_surface {
+                    return None;
+                }
+                state.is_inside = true;
+                match &self.cursor_frame {
+                    Some(cursor_frame) => cursor_frame.set_tool(serial, &tool),
+                    None => tool.set_cursor(serial, None, 0, 0),
+                }
+                ToolEvent::ProximityIn { tablet: tablet.as_ref().id() }
+            }
+            _ if !state.is_inside => return None,
+            zwp_tablet_tool_v2::Event::ProximityOut => {
+                state.is_leaving = true;
+                ToolEvent::ProximityOut
+            }
+            zwp_tablet_tool_v2::Event::Down { .. } => ToolEvent::Down,
+            zwp_tablet_tool_v2::Event::Up => ToolEvent::Up,
+            zwp_tablet_tool_v2::Event::Motion { x, y } => ToolEvent::Motion { x, y },
+            zwp_tablet_tool_v2::Event::Pressure { pressure } => ToolEvent::Pressure(pressure as f64 / 65535.0),
+            zwp_tablet_tool_v2::Event::Distance { distance } => ToolEvent::Distance(distance as f64 / 65535.0),
+            zwp_tablet_tool_v2::Event::Tilt { tilt_x, tilt_y } => ToolEvent::Tilt { x: tilt_x, y: tilt_y },
+            zwp_tablet_tool_v2::Event::Rotation { degrees } => ToolEvent::Rotation(degrees),
+            zwp_tablet_tool_v2::Event::Slider { position } => ToolEvent::Slider(position as f64 / 65535.0),
+            zwp_tablet_tool_v2::Event::Wheel { degrees, clicks } => ToolEvent::Wheel { degrees, clicks },
+            zwp_tablet_tool_v2::Event::Button { button, state: button_state, .. } => ToolEvent::Button {
+                button,
+                pressed: button_state == zwp_tablet_tool_v2::ButtonState::Pressed,
+            },
+            zwp_tablet_tool_v2::Event::Frame { time } => {
+                if state.is_leaving {
+                    state.is_inside = false;
+                    state.is_leaving = false;
+                }
+                ToolEvent::Frame { time }
+            }
+            _ => return None,
+        };
+        Some(TabletEvent::Tool(id, tool_event))
+    }
+    fn handle_pad_event(&mut self, evt: zwp_tablet_pad_v2::Event, pad: Main<ZwpTabletPadV2>) -> Option<TabletEvent> {
+        let id = pad.as_ref().id();
+        let state = self.pads.entry(id).or_default();
+        match evt {
+            zwp_tablet_pad_v2::Event::Path { path } => state.desc.paths.push(path),
+            zwp_tablet_pad_v2::Event::Buttons { buttons } => state.desc.buttons = buttons,
+            zwp_tablet_pad_v2::Event::Done => return Some(TabletEvent::PadAdded(id, state.desc.clone())),
+            zwp_tablet_pad_v2::Event::Enter { surface, .. } => state.is_inside = surface == self.wl_surface,
+            zwp_tablet_pad_v2::Event::Leave { surface, .. } => {
+                if surface == self.wl_surface {
+                    state.is_inside = false;
+                }
+            }
+            zwp_tablet_pad_v2::Event::Button { button, state: button_state, .. } if state.is_inside => {
+                return Some(TabletEvent::PadButton {
+                    pad: id,
+                    button,
+                    pressed: button_state == zwp_tablet_pad_v2::ButtonState::Pressed,
+                });
+            }
+            zwp_tablet_pad_v2::Event::Removed => {
+                self.pads.remove(&id);
+                pad.destroy();
+                return Some(TabletEvent::PadRemoved(id));
+            }
+            _ => (),
+        }
+        None
+    }
+}
+
+/// Tablet input on a surface, the tablet counterpart of [`PointerInfo`](super::cursor::PointerInfo).
+#[derive(Clone)]
+pub struct TabletInfo {
+    inner: RcCell<TabletInfoInner>,
+}
+
+impl std::fmt::Debug for TabletInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let inner = self.inner.borrow();
+        f.debug_struct(std::any::type_name::<Self>())
+            .field("wl_surface", &inner.wl_surface)
+            .field("cursor_frame", &inner.cursor_frame)
+            .field("tablets", &inner.tablets)
+            .field("tools", &inner.tools)
+            .field("pads", &inner.pads)
+            .field("handler", &"Cool closure")
+            .finish()
+    }
+}
+
+impl TabletInfo {
+    /// Returns `None` if the compositor doesn't support tablets.
+    pub fn new(cursor_name: &str, parent_surface: WlSurface) -> Option<Self> {
+        let globals = get_globals();
+        let tablet_seat = globals.tablet_manager.as_ref()?.get_tablet_seat(&globals.wl_seat);
+        let this = Self {
+            inner: TabletInfoInner {
+                wl_surface: parent_surface,
+                cursor_frame: CursorFrame::new(globals, cursor_name),
+                tablets: HashMap::new(),
+                tools: HashMap::new(),
+                pads: HashMap::new(),
+                handler: None,
+            }
+            .into(),
+        };
+        tablet_seat.quick_assign({
+            let this = this.clone();
+            move |_: Main<ZwpTabletSeatV2>, evt, _| this.handle_seat_event(evt)
+        });
+        Some(this)
+    }
+    pub fn on_event(self, on_event: impl TabletCb + 'static) -> Self {
+        self.inner.borrow_mut().handler = Some(Rc::new(on_event));
+        self
+    }
+    fn handle_seat_event(&self, evt: zwp_tablet_seat_v2::Event) {
+        match evt {
+            zwp_tablet_seat_v2::Event::TabletAdded { id } => id.quick_assign({
+                let this = self.clone();
+                move |tablet, evt, _| this.dispatch(|inner| inner.handle_tablet_event(evt, tablet))
+            }),
+            zwp_tablet_seat_v2::Event::ToolAdded { id } => id.quick_assign({
+                let this = self.clone();
+                move |tool, evt, _| this.dispatch(|inner| inner.handle_tool_event(evt, tool))
+            }),
+            zwp_tablet_seat_v2::Event::PadAdded { id } => id.quick_assign({
+                let this = self.clone();
+                move |pad, evt, _| this.dispatch(|inner| inner.handle_pad_event(evt, pad))
+            }),
+            _ => (),
+        }
+    }
+    /// Runs the handler outside of the borrow, so it can use this `TabletInfo`
+    fn dispatch(&self, handle: impl FnOnce(&mut TabletInfoInner) -> Option<TabletEvent>) {
+        let (evt, handler) = {
+            let mut inner = self.inner.borrow_mut();
+            (handle(&mut inner), inner.handler.clone())
+        };
+        if let (Some(evt), Some(handler)) = (evt, handler) {
+            handler(evt)
+        }
+    }
+}
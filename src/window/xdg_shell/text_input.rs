@@ -0,0 +1,286 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use wayland_client::protocol::wl_surface::WlSurface;
+use wayland_client::Main;
+use wayland_protocols::unstable::text_input::v3::client::zwp_text_input_v3::{self, ZwpTextInputV3};
+
+pub use wayland_protocols::unstable::text_input::v3::client::zwp_text_input_v3::{
+    ChangeCause, ContentHint, ContentPurpose,
+};
+
+use crate::get_globals;
+use crate::prelude::*;
+
+// Longest surrounding text the protocol accepts, in bytes
+const MAX_SURROUNDING_TEXT: usize = 4000;
+
+/// Text being composed by the input method, not yet part of the widget's text
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Preedit {
+    pub text: String,
+    /// Byte range of `text` to show as the cursor, `None` hides the cursor
+    pub cursor: Option<(usize, usize)>,
+}
+
+/// Implemented by text widgets to get input method text
+pub trait TextInputClient {
+    /// Text around the cursor, and byte offsets in it of the cursor and the selection anchor
+    fn surrounding_text(&self) -> (String, usize, usize);
+    /// Where the cursor is drawn, in surface coordinates, so candidates can show up next to it
+    fn cursor_rect(&self) -> Rect;
+    fn content_type(&self) -> (ContentHint, ContentPurpose) {
+        (ContentHint::None, ContentPurpose::Normal)
+    }
+    fn set_preedit(&mut self, preedit: Option<Preedit>);
+    fn commit_string(&mut self, text: &str);
+    /// Lengths in bytes, before and after the cursor
+    fn delete_surrounding_text(&mut self, before: usize, after: usize);
+}
+
+pub trait ChangeCb = Fn();
+
+/// Changes sent by the input method, applied all at once on `done`
+#[derive(Debug, Default)]
+struct PendingState {
+    preedit: Option<Preedit>,
+    commit: Option<String>,
+    delete: Option<(usize, usize)>,
+}
+
+struct TextInputInner {
+    wl_surface: WlSurface,
+    text_input: Main<ZwpTextInputV3>,
+    is_inside: bool,
+    client: Option<Rc<RefCell<dyn TextInputClient>>>,
+    pending: PendingState,
+    on_change: Option<Rc<dyn ChangeCb>>,
+}
+
+impl TextInputInner {
+    fn enable(&self) {
+        self.text_input.enable();
+    }
+    fn disable(&self) {
+        self.text_input.disable();
+        self.text_input.commit();
+    }
+    fn send_state(&self, cause: ChangeCause) {
+        let Some(client) = &self.client else {
+            return;
+        };
+        let client = client.borrow();
+        let (text, cursor, anchor) = client.surrounding_text();
+        let (text, cursor, anchor) = clamp_surrounding_text(&text, cursor, anchor);
+        self.text_input.set_surrounding_text(text, cursor as i32, anchor as i32);
+        self.text_input.set_text_change_cause(cause);
+        let (hint, purpose) = client.content_type();
+        self.text_input.set_content_type(hint, purpose);
+        let rect = client.cursor_rect();
+        self.text_input.set_cursor_rectangle(rect.x, rect.y, rect.width, rect.height);
+        self.text_input.commit();
+    }
+    fn handle_event(&mut self, evt: zwp_text_input_v3::Event) -> Option<PendingState> {
+        match evt {
+            zwp_text_input_v3::Event::Enter { surface } if surface == self.wl_surface => {
+                self.is_inside = true;
+                if self.client.is_some() {
+                    self.enable();
+                    self.send_state(ChangeCause::Other);
+                }
+            }
+            zwp_text_input_v3::Event::Leave { surface } if surface == self.wl_surface => {
+                self.is_inside = false;
+                self.pending = PendingState::default();
+                self.disable();
+            }
+            zwp_text_input_v3::Event::PreeditString { text, cursor_begin, cursor_end } => {
+                self.pending.preedit = text.map(|text| Preedit {
+                    text,
+                    cursor: (cursor_begin >= 0 && cursor_end >= 0)
+                        .then_some((cursor_begin as usize, cursor_end as usize)),
+                });
+            }
+            zwp_text_input_v3::Event::CommitString { text } => self.pending.commit = text,
+            zwp_text_input_v3::Event::DeleteSurroundingText { before_length, after_length } => {
+                self.pending.delete = Some((before_length as usize, after_length as usize));
+            }
+            zwp_text_input_v3::Event::Done { .. } if self.is_inside => {
+                return Some(std::mem::take(&mut self.pending));
+            }
+            _ => (),
+        }
+        None
+    }
+}
+
+/// Keeps at most `MAX_SURROUNDING_TEXT` bytes of `text` around the cursor and anchor. A
+/// selection longer than that is cut short on the anchor's side.
+fn clamp_surrounding_text(text: &str, cursor: usize, anchor: usize) -> (String, usize, usize) {
+    if text.len() <= MAX_SURROUNDING_TEXT {
+        return (text.to_owned(), cursor, anchor);
+    }
+    let mut anchor = anchor;
+    if anchor > cursor + MAX_SURROUNDING_TEXT {
+        anchor = cursor + MAX_SURROUNDING_TEXT;
+        while !text.is_char_boundary(anchor) {
+            anchor -= 1;
+        }
+    } else if anchor + MAX_SURROUNDING_TEXT < cursor {
+        anchor = cursor - MAX_SURROUNDING_TEXT;
+        while !text.is_char_boundary(anchor) {
+            anchor += 1;
+        }
+    }
+    let (low, high) = (cursor.min(anchor), cursor.max(anchor));
+    let slack = MAX_SURROUNDING_TEXT.saturating_sub(high - low) / 2;
+    let mut start = low.saturating_sub(slack);
+    let mut end = (high + slack).min(text.len());
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    let start = start.min(low);
+    (text[start..end].to_owned(), cursor - start, anchor - start)
+}
+
+/// Input method support for a surface. Text widgets get the input method text
+/// while they are given focus through [`TextInput::focus`].
+#[derive(Clone)]
+pub struct TextInput {
+    inner: RcCell<TextInputInner>,
+}
+
+impl std::fmt::Debug for TextInput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let inner = self.inner.borrow();
+        f.debug_struct(std::any::type_name::<Self>())
+            .field("wl_surface", &inner.wl_surface)
+            .field("text_input", &inner.text_input)
+            .field("is_inside", &inner.is_inside)
+            .field("client", &inner.client.as_ref().map(|_| "Cool widget"))
+            .field("pending", &inner.pending)
+            .field("on_change", &"Cool closure")
+            .finish()
+    }
+}
+
+impl TextInput {
+    /// Returns `None` if the compositor doesn't support input methods.
+    pub fn new(parent_surface: WlSurface) -> Option<Self> {
+        let globals = get_globals();
+        let text_input = globals.text_input_manager.as_ref()?.get_text_input(&globals.wl_seat);
+        let this = Self {
+            inner: TextInputInner {
+                wl_surface: parent_surface,
+                text_input: text_input.clone(),
+                is_inside: false,
+                client: None,
+                pending: PendingState::default(),
+                on_change: None,
+            }
+            .into(),
+        };
+        text_input.quick_assign({
+            let this = this.clone();
+            move |_, evt, _| {
+                let pending = this.inner.borrow_mut().handle_event(evt);
+                if let Some(pending) = pending {
+                    this.apply(pending);
+                }
+            }
+        });
+        Some(this)
+    }
+    /// Called once input method changes were applied to the focused widget, with nothing
+    /// borrowed, so the window can redraw it.
+    pub fn on_change(self, on_change: impl ChangeCb + 'static) -> Self {
+        self.inner.borrow_mut().on_change = Some(Rc::new(on_change));
+        self
+    }
+    /// Sets the widget getting input method text, `None` when no text widget has focus.
+    pub fn focus(&self, client: Option<Rc<RefCell<dyn TextInputClient>>>) {
+        let old = self.inner.borrow_mut().client.take();
+        if let Some(old) = old {
+            old.borrow_mut().set_preedit(None);
+        }
+        let mut inner = self.inner.borrow_mut();
+        let focused = client.is_some();
+        inner.client = client;
+        if !inner.is_inside {
+            return;
+        }
+        if focused {
+            inner.enable();
+            inner.send_state(ChangeCause::Other);
+        } else {
+            inner.disable();
+        }
+    }
+    /// Must be called whenever the focused widget's text, cursor or content type changed,
+    /// other than through [`TextInputClient`]. The widget must not be borrowed.
+    pub fn update(&self) {
+        let inner = self.inner.borrow();
+        if inner.is_inside {
+            inner.send_state(ChangeCause::Other);
+        }
+    }
+    /// Applies the input method changes in the order the protocol asks for
+    fn apply(&self, pending: PendingState) {
+        let Some(client) = self.inner.borrow().client.clone() else {
+            return;
+        };
+        {
+            let mut client = client.borrow_mut();
+            client.set_preedit(None);
+            if let Some((before, after)) = pending.delete {
+                client.delete_surrounding_text(before, after);
+            }
+            if let Some(commit) = pending.commit {
+                client.commit_string(&commit);
+            }
+            client.set_preedit(pending.preedit);
+        }
+        let on_change = {
+            let inner = self.inner.borrow();
+            inner.send_state(ChangeCause::InputMethod);
+            inner.on_change.clone()
+        };
+        if let Some(on_change) = on_change {
+            on_change();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_text_is_kept() {
+        assert_eq!(clamp_surrounding_text("héllo", 3, 1), ("héllo".to_owned(), 3, 1));
+    }
+
+    #[test]
+    fn long_text_is_cut_around_the_cursor() {
+        let text = "é".repeat(3000);
+        let (clamped, cursor, anchor) = clamp_surrounding_text(&text, 3000, 3000);
+        assert!(clamped.len() <= MAX_SURROUNDING_TEXT);
+        assert_eq!(cursor, anchor);
+        assert_eq!(&clamped[..cursor], &text[3000 - cursor..3000]);
+    }
+
+    #[test]
+    fn long_selection_keeps_the_cursor() {
+        // 3 bytes per char, so the anchor lands inside one and has to be moved to its edge
+        let text = "€".repeat(4000);
+        let (clamped, cursor, anchor) = clamp_surrounding_text(&text, 9000, 0);
+        assert!(clamped.len() <= MAX_SURROUNDING_TEXT);
+        assert_eq!(&clamped[anchor..cursor], &text[5001..9000]);
+        let (clamped, cursor, anchor) = clamp_surrounding_text(&text, 3000, 12000);
+        assert!(clamped.len() <= MAX_SURROUNDING_TEXT);
+        assert_eq!(&clamped[cursor..anchor], &text[3000..6999]);
+    }
+}
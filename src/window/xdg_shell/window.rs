@@ -1,15 +1,28 @@
-use std::cell::RefCell;
-use std::rc::Rc;
+use std::cell::{Cell, RefCell};
+use std::rc::{Rc, Weak};
+use std::time::Instant;
+use wayland_client::protocol::wl_seat::WlSeat;
 use wayland_client::Main;
 use wayland_protocols::xdg_shell::client::{
     xdg_surface::{Event as XdgSurfaceEvent, XdgSurface},
-    xdg_toplevel::{Event as XdgToplevelEvent, XdgToplevel},
+    xdg_toplevel::{Event as XdgToplevelEvent, State as XdgToplevelState, XdgToplevel},
 };
 
+use crate::data_device::{DndAction, DragSource, DropCb};
+use crate::keyboard::KeyboardEvent;
+use crate::prelude::Rect;
+use crate::window::pointer_constraints::{
+    ConstraintCb, Lifetime, PointerConstraint, RelativeMotionCb, RelativePointer,
+};
+use crate::window::shortcuts_inhibit::{InhibitCb, ShortcutsInhibitor};
+use crate::widget::{Deferred, Theme, WidgetTree};
 use crate::window::{WindowBackend, WindowCommon};
 
 use super::{GlobalsHandle, XdgGlobals};
 use super::cursor::PointerInfo;
+use super::gestures::GestureCb;
+use super::tablet::{TabletCb, TabletInfo};
+use super::text_input::TextInput;
 use super::frame::XdgWindowFrame;
 
 pub struct XdgWindowInner {
@@ -17,15 +30,29 @@ pub struct XdgWindowInner {
     pub xdg_surface: Main<XdgSurface>,
     pub xdg_toplevel: Main<XdgToplevel>,
     pointer: PointerInfo,
+    tablet: Option<TabletInfo>,
+    text_input: Option<TextInput>,
+    shortcuts_inhibitors: RefCell<Vec<ShortcutsInhibitor>>,
     pub window: RefCell<WindowCommon>,
     frame: RefCell<XdgWindowFrame>,
+    /// Set with [`XdgWindow::set_corner_radius`], `None` follows the theme
+    corner_radius: Cell<Option<i32>>,
+    /// Maximized, tiled or fullscreen, where rounded corners would leave gaps at the edges
+    square_corners: Cell<bool>,
 }
 
 impl Drop for XdgWindowInner {
     fn drop(&mut self) {
         self.xdg_toplevel.destroy();
         self.xdg_surface.destroy();
-        let window = self.window.borrow_mut();
+        self.shortcuts_inhibitors.borrow_mut().clear();
+        let mut window = self.window.borrow_mut();
+        self.globals.keyboard.remove_target(&window.surface);
+        self.globals.config.remove_target(&window.surface);
+        if let Some(data_device) = &self.globals.data_device {
+            data_device.remove_drop_target(&window.surface);
+        }
+        window.set_idle_inhibit(false);
         window.surface.destroy();
     }
 }
@@ -47,7 +74,7 @@ impl WindowBackend for XdgWindow {
         let xdg_toplevel = xdg_surface.get_toplevel();
         surface.commit();
 
-        let frame = XdgWindowFrame::new(&surface, 20, globals.clone());
+        let frame = XdgWindowFrame::new(&surface, globals.theme().border_width as u32, globals.clone());
         surface.commit();
 
         globals.display.flush().unwrap();
@@ -57,6 +84,11 @@ impl WindowBackend for XdgWindow {
             xdg_toplevel,
             frame: RefCell::new(frame),
             window: window.into(),
+            tablet: TabletInfo::new("crosshair", surface.clone()),
+            text_input: TextInput::new(surface.clone()),
+            shortcuts_inhibitors: RefCell::default(),
+            corner_radius: Cell::default(),
+            square_corners: Cell::default(),
             pointer: PointerInfo::new("left_ptr", surface),
             globals,
         };
@@ -69,23 +101,251 @@ impl WindowBackend for XdgWindow {
     fn window_common(&self) -> WindowCommon {
         self.inner.window.borrow().clone()
     }
+    fn update(&self) {
+        let (needs_redraw, animating) = {
+            let widgets = self.widgets();
+            let widgets = widgets.borrow();
+            (widgets.needs_redraw(), widgets.is_animating())
+        };
+        let mut window = self.inner.window.borrow_mut();
+        let frame_requested = animating
+            && window.request_frame({
+                let this = self.clone();
+                move || this.dispatch(|widgets| widgets.animation_frame(Instant::now()))
+            });
+        let committed = needs_redraw && window.redraw();
+        // The frame callback only comes after a commit
+        if frame_requested && !committed {
+            window.surface.commit();
+        }
+    }
+    fn set_idle_inhibit(&self, inhibit: bool) -> bool {
+        self.inner.window.borrow_mut().set_idle_inhibit(inhibit)
+    }
 }
 
 impl XdgWindow {
+    /// Returns `None` if the compositor can't constrain the pointer.
+    pub fn lock_pointer(
+        &self,
+        lifetime: Lifetime,
+        region: Option<&[Rect]>,
+        on_state: impl ConstraintCb + 'static,
+    ) -> Option<PointerConstraint> {
+        let surface = self.inner.window.borrow().surface.clone();
+        let pointer = self.inner.pointer.wl_pointer();
+        PointerConstraint::lock(&self.inner.globals, &surface, &pointer, lifetime, region, on_state)
+    }
+    /// Returns `None` if the compositor can't constrain the pointer.
+    pub fn confine_pointer(
+        &self,
+        lifetime: Lifetime,
+        region: Option<&[Rect]>,
+        on_state: impl ConstraintCb + 'static,
+    ) -> Option<PointerConstraint> {
+        let surface = self.inner.window.borrow().surface.clone();
+        let pointer = self.inner.pointer.wl_pointer();
+        PointerConstraint::confine(&self.inner.globals, &surface, &pointer, lifetime, region, on_state)
+    }
+    /// Returns `None` if the compositor doesn't report relative motion.
+    pub fn relative_pointer(&self, on_motion: impl RelativeMotionCb + 'static) -> Option<RelativePointer> {
+        RelativePointer::new(&self.inner.globals, &self.inner.pointer.wl_pointer(), on_motion)
+    }
+
+    pub fn on_gesture(&self, on_gesture: impl GestureCb + 'static) {
+        self.inner.pointer.clone().on_gesture(on_gesture);
+    }
+
+    /// Does nothing if the compositor doesn't support tablets.
+    pub fn on_tablet_event(&self, on_event: impl TabletCb + 'static) {
+        if let Some(tablet) = &self.inner.tablet {
+            tablet.clone().on_event(on_event);
+        }
+    }
+
+    /// Lets the window get the keys the compositor uses for its shortcuts, like Super or
+    /// Alt-Tab, while it has `seat`'s keyboard focus. `on_state` reports when the compositor
+    /// grants or revokes it. Returns `false` if the compositor doesn't support it.
+    pub fn inhibit_shortcuts(&self, seat: &WlSeat, on_state: impl InhibitCb + 'static) -> bool {
+        let mut inhibitors = self.inner.shortcuts_inhibitors.borrow_mut();
+        // Replacing it has to go through destroying the old one first
+        inhibitors.retain(|inhibitor| inhibitor.seat() != seat);
+        let surface = self.inner.window.borrow().surface.clone();
+        let Some(inhibitor) = ShortcutsInhibitor::new(&self.inner.globals, &surface, seat, on_state) else {
+            return false;
+        };
+        inhibitors.push(inhibitor);
+        true
+    }
+    pub fn restore_shortcuts(&self, seat: &WlSeat) {
+        self.inner.shortcuts_inhibitors.borrow_mut().retain(|inhibitor| inhibitor.seat() != seat);
+    }
+    pub fn shortcuts_inhibited(&self, seat: &WlSeat) -> bool {
+        let inhibitors = self.inner.shortcuts_inhibitors.borrow();
+        inhibitors.iter().any(|inhibitor| inhibitor.seat() == seat && inhibitor.is_active())
+    }
+
+    /// Takes the drops on the window, instead of its widgets. Does nothing if the compositor
+    /// has no data device.
+    pub fn on_drop(&self, on_drop: impl DropCb + 'static) {
+        if let Some(data_device) = &self.inner.globals.data_device {
+            let surface = self.inner.window.borrow().surface.clone();
+            data_device.set_drop_target(&surface, on_drop);
+        }
+    }
+    /// Drag out of the window, started with [`DragSource::start`]. `None` if the compositor
+    /// has no data device. Widgets start theirs with `EventCtx::drag`.
+    pub fn drag(&self, mime_types: &[&str], actions: DndAction) -> Option<DragSource> {
+        let surface = self.inner.window.borrow().surface.clone();
+        let data_device = self.inner.globals.data_device.as_ref()?;
+        Some(data_device.drag(&surface, mime_types, actions))
+    }
+
+    /// Rounds the corners of the window and its frame by `radius`, `None` uses the one of the
+    /// theme. They stay square while the window is maximized, tiled or fullscreen.
+    pub fn set_corner_radius(&self, radius: Option<i32>) {
+        self.inner.corner_radius.set(radius);
+        let theme = self.widgets().borrow().theme().clone();
+        self.apply_corner_radius(&theme);
+        self.update();
+    }
+    fn apply_corner_radius(&self, theme: &Theme) {
+        let radius = match self.inner.square_corners.get() {
+            true => 0,
+            false => self.inner.corner_radius.get().unwrap_or(theme.corner_radius),
+        };
+        self.inner.window.borrow_mut().set_corner_radius(radius);
+        self.inner.frame.borrow_mut().set_corner_radius(radius);
+    }
+
+    /// Lays the widgets out again and repaints the window and its frame with `theme`
+    pub fn set_theme(&self, theme: &Theme) {
+        self.inner.frame.borrow_mut().set_theme(theme);
+        self.apply_corner_radius(theme);
+        self.dispatch(|widgets| {
+            widgets.set_theme(theme.clone());
+            Vec::new()
+        });
+    }
+
+    /// `None` if the compositor doesn't support input methods.
+    pub fn text_input(&self) -> Option<TextInput> {
+        self.inner.text_input.clone()
+    }
+
+    /// Runs `event` on the widgets, then what they deferred. Afterwards the window is
+    /// redrawn if needed, and the input method follows the focused widget.
+    fn dispatch(&self, event: impl FnOnce(&mut WidgetTree) -> Vec<Deferred>) {
+        let widgets = self.widgets();
+        let focused = widgets.borrow().focused();
+        let deferred = event(&mut widgets.borrow_mut());
+        for deferred in deferred {
+            deferred();
+        }
+        self.update();
+        let Some(text_input) = &self.inner.text_input else {
+            return;
+        };
+        let (now_focused, client) = {
+            let widgets = widgets.borrow();
+            (widgets.focused(), widgets.text_input_client())
+        };
+        if now_focused != focused {
+            text_input.focus(client);
+        } else if client.is_some() {
+            text_input.update();
+        }
+    }
+
+    /// The window behind `weak`, if it wasn't dropped
+    fn upgrade(weak: &Weak<XdgWindowInner>) -> Option<Self> {
+        weak.upgrade().map(|inner| Self { inner })
+    }
+
     fn register_callbacks(&self) {
         let this = &self.inner;
+        this.pointer.clone().on_pointer_event({
+            let this = self.clone();
+            move |evt| this.dispatch(|widgets| widgets.pointer_event(evt))
+        });
+        let surface = this.window.borrow().surface.clone();
+        // The globals outlive the window, and would keep it alive through strong handles
+        this.globals.keyboard.set_target(&surface, {
+            let weak = Rc::downgrade(&self.inner);
+            move |evt| {
+                let Some(this) = Self::upgrade(&weak) else {
+                    return;
+                };
+                match evt {
+                    KeyboardEvent::Enter { .. } => this.dispatch(WidgetTree::keyboard_enter),
+                    KeyboardEvent::Leave => this.dispatch(WidgetTree::keyboard_leave),
+                    KeyboardEvent::Key(key) => this.dispatch(|widgets| widgets.key_event(key)),
+                }
+            }
+        });
+        this.globals.config.set_target(&surface, {
+            let weak = Rc::downgrade(&self.inner);
+            move |theme| {
+                if let Some(this) = Self::upgrade(&weak) {
+                    this.set_theme(theme)
+                }
+            }
+        });
+        if let Some(data_device) = &this.globals.data_device {
+            self.widgets().borrow_mut().set_drag_origin(data_device.clone(), surface.detach());
+            data_device.set_drop_target(&surface, {
+                let weak = Rc::downgrade(&self.inner);
+                move |evt| {
+                    if let Some(this) = Self::upgrade(&weak) {
+                        this.dispatch(|widgets| widgets.drop_event(evt))
+                    }
+                }
+            });
+        }
+        if let Some(text_input) = &this.text_input {
+            text_input.clone().on_change({
+                let this = self.clone();
+                move || {
+                    // The input method already knows about its own changes
+                    let deferred = this.widgets().borrow_mut().input_method_event();
+                    for deferred in deferred {
+                        deferred();
+                    }
+                    this.update();
+                }
+            });
+        }
         this.xdg_toplevel.quick_assign({
             let this = self.clone();
             move |_toplevel: Main<XdgToplevel>, evt: XdgToplevelEvent, _globals: _| match evt {
                 XdgToplevelEvent::Configure {
                     mut width,
                     mut height,
-                    states: _,
+                    states,
                 } => {
                     if width == 0 || height == 0 {
                         width = 320;
                         height = 320;
                     }
+                    let square = states
+                        .chunks_exact(4)
+                        .filter_map(|state| {
+                            XdgToplevelState::from_raw(u32::from_ne_bytes(state.try_into().unwrap()))
+                        })
+                        .any(|state| {
+                            matches!(
+                                state,
+                                XdgToplevelState::Maximized
+                                    | XdgToplevelState::Fullscreen
+                                    | XdgToplevelState::TiledLeft
+                                    | XdgToplevelState::TiledRight
+                                    | XdgToplevelState::TiledTop
+                                    | XdgToplevelState::TiledBottom
+                            )
+                        });
+                    this.inner.square_corners.set(square);
+                    let theme = this.widgets().borrow().theme().clone();
+                    this.apply_corner_radius(&theme);
                     this.inner.window.borrow_mut().resize(width, height);
                     this.inner.frame.borrow_mut().move_(width, height);
 